@@ -0,0 +1,70 @@
+//! How many allocations and how long it takes to build requests.
+//!
+//! Run with `cargo bench -p ehttp --bench request`.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+/// Counts the allocations, and otherwise leaves them to the system allocator.
+struct CountingAllocator;
+
+static NUM_ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        NUM_ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+const NUM_REQUESTS: usize = 1_000_000;
+
+fn bench(name: &str, mut create: impl FnMut() -> ehttp::Request) {
+    let allocations_before = NUM_ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    for _ in 0..NUM_REQUESTS {
+        std::hint::black_box(create());
+    }
+    let elapsed = start.elapsed();
+    let allocations = NUM_ALLOCATIONS.load(Ordering::Relaxed) - allocations_before;
+    println!(
+        "{name:<40} {:>6.2} allocations, {:>6.0} ns per request",
+        allocations as f64 / NUM_REQUESTS as f64,
+        elapsed.as_nanos() as f64 / NUM_REQUESTS as f64
+    );
+}
+
+fn main() {
+    let method_allocations = |method: ehttp::Method| {
+        let before = NUM_ALLOCATIONS.load(Ordering::Relaxed);
+        let method: std::borrow::Cow<'static, str> = std::hint::black_box(method).into();
+        std::hint::black_box(method);
+        NUM_ALLOCATIONS.load(Ordering::Relaxed) - before
+    };
+    for method in ehttp::Method::ALL {
+        assert_eq!(
+            method_allocations(method),
+            0,
+            "A standard method should not allocate"
+        );
+    }
+
+    bench("Request::get", || {
+        ehttp::Request::get("https://www.example.com")
+    });
+    bench("Request::get + with_method(Put)", || {
+        ehttp::Request::get("https://www.example.com").with_method(ehttp::Method::Put)
+    });
+    bench("Request::get + with_method(Other)", || {
+        ehttp::Request::get("https://www.example.com")
+            .with_method(ehttp::Method::Other("PROPFIND".to_owned()))
+    });
+}
@@ -0,0 +1,126 @@
+use crate::method::is_token;
+
+/// An authentication scheme and its parameters,
+/// as found in an `Authorization` or `WWW-Authenticate` header.
+///
+/// See [`parse_authorization`] and [`crate::Response::www_authenticate`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AuthScheme {
+    /// E.g. `Basic` or `Bearer`.
+    pub scheme: String,
+
+    /// The credentials of e.g. `Basic dXNlcjpwYXNz`, if given in the `token68` form.
+    pub token: Option<String>,
+
+    /// `name=value` parameters, e.g. `realm="example"`, with quotes removed.
+    pub params: Vec<(String, String)>,
+}
+
+impl AuthScheme {
+    /// Get the value of the given parameter, e.g. `realm`.
+    ///
+    /// The lookup is case-insensitive.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// Parse the value of an `Authorization` or `WWW-Authenticate` header.
+///
+/// If the header contains several challenges, only the first one is returned.
+///
+/// ```
+/// use ehttp::parse_authorization;
+///
+/// let basic = parse_authorization(r#"Basic realm="Staging", charset="UTF-8""#).unwrap();
+/// assert_eq!(basic.scheme, "Basic");
+/// assert_eq!(basic.param("realm"), Some("Staging"));
+/// assert_eq!(basic.param("charset"), Some("UTF-8"));
+///
+/// let bearer = parse_authorization(
+///     r#"Bearer realm="api", error="invalid_token", error_description="The token \"abc\" expired""#,
+/// )
+/// .unwrap();
+/// assert_eq!(bearer.scheme, "Bearer");
+/// assert_eq!(bearer.param("Realm"), Some("api"));
+/// assert_eq!(bearer.param("error_description"), Some(r#"The token "abc" expired"#));
+///
+/// let credentials = parse_authorization("Basic dXNlcjpwYXNz").unwrap();
+/// assert_eq!(credentials.token.as_deref(), Some("dXNlcjpwYXNz"));
+///
+/// let first = parse_authorization(r#"Basic realm="a", Bearer realm="b""#).unwrap();
+/// assert_eq!(first.scheme, "Basic");
+/// assert_eq!(first.params.len(), 1);
+///
+/// assert_eq!(parse_authorization(""), None);
+/// ```
+pub fn parse_authorization(value: &str) -> Option<AuthScheme> {
+    let value = value.trim();
+    let (scheme, rest) = value.split_once(' ').unwrap_or((value, ""));
+    if !is_token(scheme) {
+        return None;
+    }
+    let rest = rest.trim();
+
+    let mut auth = AuthScheme {
+        scheme: scheme.to_owned(),
+        token: None,
+        params: vec![],
+    };
+
+    if is_token68(rest) {
+        auth.token = Some(rest.to_owned());
+        return Some(auth);
+    }
+
+    let mut rest = rest;
+    while let Some((name, value, remaining)) = parse_param(rest) {
+        auth.params.push((name, value));
+        let remaining = remaining.trim_start();
+        match remaining.strip_prefix(',') {
+            Some(remaining) => rest = remaining.trim_start(),
+            None => break,
+        }
+    }
+
+    Some(auth)
+}
+
+fn is_token68(s: &str) -> bool {
+    let data = s.trim_end_matches('=');
+    !data.is_empty()
+        && data
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"-._~+/".contains(&b))
+}
+
+/// Parse `name=value` or `name="quoted value"`, returning the rest of the input.
+fn parse_param(s: &str) -> Option<(String, String, &str)> {
+    let (name, rest) = s.split_once('=')?;
+    let name = name.trim();
+    if !is_token(name) {
+        return None;
+    }
+    let rest = rest.trim_start();
+
+    if let Some(quoted) = rest.strip_prefix('"') {
+        let mut value = String::new();
+        let mut chars = quoted.char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '\\' => value.push(chars.next()?.1),
+                '"' => return Some((name.to_owned(), value, &quoted[i + 1..])),
+                c => value.push(c),
+            }
+        }
+        None // Unterminated quoted string
+    } else {
+        let end = rest
+            .find(|c: char| c == ',' || c.is_whitespace())
+            .unwrap_or(rest.len());
+        Some((name.to_owned(), rest[..end].to_owned(), &rest[end..]))
+    }
+}
@@ -0,0 +1,72 @@
+/// The version of `ehttp` and the features it was compiled with.
+///
+/// See [`build_info`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BuildInfo {
+    /// The crate version, e.g. `0.4.1`.
+    pub version: &'static str,
+
+    /// The enabled feature flags, e.g. `["json", "multipart"]`.
+    pub features: Vec<&'static str>,
+}
+
+impl BuildInfo {
+    /// Was `ehttp` compiled with the given feature flag?
+    pub fn has_feature(&self, feature: &str) -> bool {
+        self.features.contains(&feature)
+    }
+}
+
+/// The version of `ehttp` and the features it was compiled with.
+///
+/// Useful for diagnostics and bug reports.
+///
+/// ```
+/// let info = ehttp::build_info();
+/// assert_eq!(info.version, env!("CARGO_PKG_VERSION"));
+/// println!("ehttp {} with features {:?}", info.version, info.features);
+/// ```
+///
+/// Every feature in `Cargo.toml` is listed:
+///
+/// ```
+/// let manifest = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml"));
+/// let source = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/src/build_info.rs"));
+/// let features = manifest
+///     .split("\n[features]\n").nth(1).unwrap()
+///     .split("\n[").next().unwrap()
+///     .lines()
+///     .filter(|line| !line.starts_with('#'))
+///     .filter_map(|line| line.split_once(" = "))
+///     .map(|(name, _)| name)
+///     .filter(|&name| name != "default");
+/// for feature in features {
+///     let entry = format!("(\"{feature}\", cfg!(feature = \"{feature}\"))");
+///     assert!(source.contains(&entry), "build_info() is missing the {:?} feature", feature);
+/// }
+/// ```
+pub fn build_info() -> BuildInfo {
+    let features = [
+        ("bytes", cfg!(feature = "bytes")),
+        ("cbor", cfg!(feature = "cbor")),
+        ("charset", cfg!(feature = "charset")),
+        ("checksum", cfg!(feature = "checksum")),
+        ("encoders", cfg!(feature = "encoders")),
+        ("json", cfg!(feature = "json")),
+        ("mmap", cfg!(feature = "mmap")),
+        ("msgpack", cfg!(feature = "msgpack")),
+        ("multipart", cfg!(feature = "multipart")),
+        ("native-async", cfg!(feature = "native-async")),
+        ("own-client", cfg!(feature = "own-client")),
+        ("streaming", cfg!(feature = "streaming")),
+        ("ureq", cfg!(feature = "ureq")),
+    ];
+
+    BuildInfo {
+        version: env!("CARGO_PKG_VERSION"),
+        features: features
+            .iter()
+            .filter_map(|&(name, enabled)| enabled.then_some(name))
+            .collect(),
+    }
+}
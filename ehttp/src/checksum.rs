@@ -0,0 +1,124 @@
+//! Checksum verification of response bodies.
+//!
+//! Requires the `checksum` feature to be enabled.
+//!
+//! Use [`crate::Request::expect_sha256`] to verify a body as it is received,
+//! or the functions in this module to verify bytes you already have.
+//!
+//! [`crate::Response::verify_content_md5`] and [`crate::Response::verify_digest`]
+//! verify a body against the checksum headers of the response.
+
+use base64::Engine as _;
+use md5::Md5;
+use sha2::{Digest as _, Sha256};
+
+/// The lowercase hex SHA-256 digest of the given bytes.
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    to_hex(&Sha256::digest(bytes))
+}
+
+/// Check that the SHA-256 digest of `bytes` matches the given hex digest.
+///
+/// The comparison is case-insensitive.
+///
+/// ```
+/// let empty = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+/// assert!(ehttp::checksum::verify_sha256(empty, b"").is_ok());
+/// assert!(ehttp::checksum::verify_sha256(&empty.to_uppercase(), b"").is_ok());
+/// assert!(ehttp::checksum::verify_sha256(empty, b"not empty").is_err());
+/// ```
+pub fn verify_sha256(expected_hex: &str, bytes: &[u8]) -> crate::Result<()> {
+    let mut verifier = Sha256Verifier::new(expected_hex);
+    verifier.update(bytes);
+    verifier.finish()
+}
+
+/// Incrementally computes the SHA-256 digest of a body, as the chunks of it arrive.
+pub(crate) struct Sha256Verifier {
+    expected_hex: String,
+    hasher: Sha256,
+}
+
+impl Sha256Verifier {
+    pub fn new(expected_hex: &str) -> Self {
+        Self {
+            expected_hex: expected_hex.trim().to_ascii_lowercase(),
+            hasher: Sha256::new(),
+        }
+    }
+
+    pub fn update(&mut self, chunk: &[u8]) {
+        self.hasher.update(chunk);
+    }
+
+    pub fn finish(self) -> crate::Result<()> {
+        let actual_hex = to_hex(&self.hasher.finalize());
+        if actual_hex == self.expected_hex {
+            Ok(())
+        } else {
+            Err(format!(
+                "Integrity check failed: expected SHA-256 {}, got {actual_hex}",
+                self.expected_hex
+            ))
+        }
+    }
+}
+
+/// Check the value of a `Content-MD5` header, i.e. the base64 MD5 digest of the body.
+///
+/// ```
+/// assert!(ehttp::checksum::verify_content_md5("XUFAKrxLKna5cZ2REBfFkg==", b"hello").is_ok());
+/// assert!(ehttp::checksum::verify_content_md5("XUFAKrxLKna5cZ2REBfFkg==", b"world").is_err());
+/// ```
+pub fn verify_content_md5(header: &str, bytes: &[u8]) -> crate::Result<()> {
+    verify_base64("MD5", header, &Md5::digest(bytes))
+}
+
+/// Check the value of a `Digest` header, e.g. `sha-256=LPJNul+wow4m6DsqxbninhsWHlwfp0JecwQzYpOLmCQ=`.
+///
+/// The `sha-256` and `md5` digests are verified. Other algorithms are ignored.
+/// Returns `Ok(false)` if there was nothing to verify.
+///
+/// ```
+/// let header = "SHA-256=LPJNul+wow4m6DsqxbninhsWHlwfp0JecwQzYpOLmCQ=, unixsum=30637";
+/// assert_eq!(ehttp::checksum::verify_digest(header, b"hello"), Ok(true));
+/// assert!(ehttp::checksum::verify_digest(header, b"world").is_err());
+/// assert_eq!(ehttp::checksum::verify_digest("unixsum=30637", b"world"), Ok(false));
+/// ```
+pub fn verify_digest(header: &str, bytes: &[u8]) -> crate::Result<bool> {
+    let mut verified = false;
+    for entry in header.split(',') {
+        let Some((algorithm, value)) = entry.split_once('=') else {
+            continue;
+        };
+        let algorithm = algorithm.trim();
+        if algorithm.eq_ignore_ascii_case("sha-256") {
+            verify_base64("SHA-256", value, &Sha256::digest(bytes))?;
+            verified = true;
+        } else if algorithm.eq_ignore_ascii_case("md5") {
+            verify_base64("MD5", value, &Md5::digest(bytes))?;
+            verified = true;
+        }
+    }
+    Ok(verified)
+}
+
+fn verify_base64(algorithm: &str, expected_base64: &str, actual: &[u8]) -> crate::Result<()> {
+    let expected = base64::engine::general_purpose::STANDARD
+        .decode(expected_base64.trim())
+        .map_err(|err| format!("Bad {algorithm} digest {expected_base64:?}: {err}"))?;
+    if expected == actual {
+        Ok(())
+    } else {
+        let engine = base64::engine::general_purpose::STANDARD;
+        Err(format!(
+            "Integrity check failed: expected {algorithm} {}, got {}",
+            expected_base64.trim(),
+            engine.encode(actual)
+        ))
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
@@ -0,0 +1,410 @@
+//! Keeping cookies between requests, on native.
+//!
+//! `ehttp` doesn't store cookies by itself. Instead you keep a [`CookieJar`],
+//! update it from the responses you get, and add its cookies to the requests you send.
+//! On web the browser does all of this for you.
+//!
+//! With the `json` feature the jar can be saved to a file, to stay logged in between runs.
+//!
+//! ```
+//! use ehttp::cookies::CookieJar;
+//!
+//! let url = "https://www.example.com/login";
+//! let response = ehttp::Response::new(200, vec![])
+//!     .with_url(url)
+//!     .with_header("Set-Cookie", "session=abc123; Path=/; Secure; HttpOnly")
+//!     .with_header("Set-Cookie", "theme=dark; Max-Age=31536000");
+//!
+//! let mut jar = CookieJar::default();
+//! jar.update_from_response(&response, url);
+//!
+//! let mut request = ehttp::Request::get("https://www.example.com/account");
+//! if let Some(cookies) = jar.cookie_header(&request.url) {
+//!     request.headers.insert("Cookie", cookies);
+//! }
+//! assert_eq!(request.headers.get("cookie"), Some("session=abc123; theme=dark"));
+//!
+//! // Secure cookies are only sent over https:
+//! assert_eq!(jar.cookie_header("http://www.example.com/").as_deref(), Some("theme=dark"));
+//! ```
+
+use std::time::{Duration, SystemTime};
+
+use crate::Response;
+
+/// A cookie, as set by a `Set-Cookie` header.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "json", derive(serde::Deserialize, serde::Serialize))]
+pub struct Cookie {
+    pub name: String,
+    pub value: String,
+
+    /// The host the cookie was set by, or the `Domain` it was set for (without a leading `.`).
+    pub domain: String,
+
+    /// If `true` the cookie is only sent to [`Self::domain`] itself, and not to its subdomains.
+    ///
+    /// This is the case when the cookie has no `Domain` attribute.
+    pub host_only: bool,
+
+    /// The cookie is only sent for urls with this path, or paths below it.
+    pub path: String,
+
+    /// Only send the cookie over `https`.
+    pub secure: bool,
+
+    /// The cookie should not be readable by scripts. `ehttp` sends it just like any other cookie.
+    pub http_only: bool,
+
+    /// When the cookie expires, from the `Max-Age` or `Expires` attributes.
+    ///
+    /// `None` means a session cookie, which is kept until the jar is dropped.
+    pub expires: Option<SystemTime>,
+}
+
+impl Cookie {
+    /// Parse the value of a `Set-Cookie` header received from the given url.
+    ///
+    /// Returns `None` for invalid cookies, and for cookies the url is not allowed to set,
+    /// e.g. ones for another domain, or `Secure` ones over plain `http`.
+    ///
+    /// ```
+    /// use std::time::{Duration, SystemTime};
+    /// use ehttp::cookies::Cookie;
+    ///
+    /// let url = "https://shop.example.com/cart/items";
+    /// let cookie = Cookie::parse("id=42; Domain=.example.com; Expires=Sun, 06 Nov 1994 08:49:37 GMT", url).unwrap();
+    /// assert_eq!(cookie.domain, "example.com");
+    /// assert!(!cookie.host_only);
+    /// assert_eq!(cookie.path, "/cart");
+    /// assert_eq!(cookie.expires, Some(SystemTime::UNIX_EPOCH + Duration::from_secs(784111777)));
+    /// assert!(cookie.is_expired());
+    ///
+    /// let old_format = Cookie::parse("id=42; Expires=Sunday, 06-Nov-94 08:49:37 GMT", url).unwrap();
+    /// assert_eq!(old_format.expires, cookie.expires);
+    ///
+    /// assert_eq!(Cookie::parse("id=42; Domain=com", url), None);
+    /// assert_eq!(Cookie::parse("id=42; Secure", "http://shop.example.com/"), None);
+    /// ```
+    pub fn parse(set_cookie: &str, url: &str) -> Option<Self> {
+        let url = url::Url::parse(url).ok()?;
+        let host = url.host_str()?.to_ascii_lowercase();
+
+        let mut attributes = set_cookie.split(';');
+        let (name, value) = attributes.next()?.split_once('=')?;
+        let name = name.trim();
+        if name.is_empty() {
+            return None;
+        }
+
+        let mut cookie = Self {
+            name: name.to_owned(),
+            value: value.trim().to_owned(),
+            domain: host.clone(),
+            host_only: true,
+            path: default_path(url.path()),
+            secure: false,
+            http_only: false,
+            expires: None,
+        };
+
+        let mut max_age = None;
+        let mut expires = None;
+
+        for attribute in attributes {
+            let (key, value) = attribute.split_once('=').unwrap_or((attribute, ""));
+            let key = key.trim();
+            let value = value.trim();
+            if key.eq_ignore_ascii_case("expires") {
+                expires = parse_http_date(value).or(expires);
+            } else if key.eq_ignore_ascii_case("max-age") {
+                if let Ok(seconds) = value.parse::<i64>() {
+                    max_age = Some(if 0 < seconds {
+                        SystemTime::now() + Duration::from_secs(seconds as u64)
+                    } else {
+                        SystemTime::UNIX_EPOCH
+                    });
+                }
+            } else if key.eq_ignore_ascii_case("domain") {
+                let domain = value.trim_start_matches('.').to_ascii_lowercase();
+                if !domain.is_empty() {
+                    if !domain_matches(&host, &domain) || (!domain.contains('.') && domain != host)
+                    {
+                        return None; // Not allowed to set cookies for that domain
+                    }
+                    cookie.domain = domain;
+                    cookie.host_only = false;
+                }
+            } else if key.eq_ignore_ascii_case("path") {
+                if value.starts_with('/') {
+                    cookie.path = value.to_owned();
+                }
+            } else if key.eq_ignore_ascii_case("secure") {
+                cookie.secure = true;
+            } else if key.eq_ignore_ascii_case("httponly") {
+                cookie.http_only = true;
+            }
+        }
+
+        if cookie.secure && url.scheme() != "https" {
+            return None;
+        }
+
+        cookie.expires = max_age.or(expires); // `Max-Age` wins
+        Some(cookie)
+    }
+
+    /// Has the cookie expired?
+    pub fn is_expired(&self) -> bool {
+        self.expires
+            .map_or(false, |expires| expires <= SystemTime::now())
+    }
+
+    /// Should the cookie be sent with a request to this url?
+    pub fn matches(&self, url: &url::Url) -> bool {
+        let Some(host) = url.host_str() else {
+            return false;
+        };
+        let host = host.to_ascii_lowercase();
+        let domain_ok = if self.host_only {
+            host == self.domain
+        } else {
+            domain_matches(&host, &self.domain)
+        };
+        domain_ok
+            && path_matches(url.path(), &self.path)
+            && (!self.secure || url.scheme() == "https")
+            && !self.is_expired()
+    }
+}
+
+/// A collection of cookies, see the [module docs](self).
+#[derive(Clone, Debug, Default)]
+pub struct CookieJar {
+    pub cookies: Vec<Cookie>,
+}
+
+impl CookieJar {
+    /// Store the cookies of all the `Set-Cookie` headers of a response to the given url.
+    ///
+    /// A cookie replaces any previous one with the same name, domain and path.
+    /// An already expired cookie removes the previous one, which is how servers delete cookies.
+    ///
+    /// ```
+    /// let url = "https://www.example.com/";
+    /// let mut jar = ehttp::cookies::CookieJar::default();
+    /// jar.update_from_response(&ehttp::Response::new(200, vec![]).with_header("Set-Cookie", "session=abc123"), url);
+    /// assert_eq!(jar.cookies.len(), 1);
+    ///
+    /// // Logging out:
+    /// let response = ehttp::Response::new(200, vec![])
+    ///     .with_header("Set-Cookie", "session=; Expires=Thu, 01 Jan 1970 00:00:00 GMT");
+    /// jar.update_from_response(&response, url);
+    /// assert!(jar.cookies.is_empty());
+    ///
+    /// // Not allowed over plain http, nor for other domains:
+    /// let response = ehttp::Response::new(200, vec![])
+    ///     .with_header("Set-Cookie", "session=abc123; Secure")
+    ///     .with_header("Set-Cookie", "tracker=1; Domain=ads.example.net");
+    /// jar.update_from_response(&response, "http://www.example.com/");
+    /// assert!(jar.cookies.is_empty());
+    /// ```
+    pub fn update_from_response(&mut self, response: &Response, url: &str) {
+        for set_cookie in response.headers.get_all("set-cookie") {
+            if let Some(cookie) = Cookie::parse(set_cookie, url) {
+                self.insert(cookie);
+            }
+        }
+    }
+
+    /// Store a cookie, replacing any previous one with the same name, domain and path.
+    ///
+    /// An expired cookie is not stored, but still removes the previous one.
+    pub fn insert(&mut self, cookie: Cookie) {
+        self.cookies.retain(|c| {
+            !(c.name == cookie.name && c.domain == cookie.domain && c.path == cookie.path)
+        });
+        if !cookie.is_expired() {
+            self.cookies.push(cookie);
+        }
+    }
+
+    /// Remove all expired cookies.
+    pub fn remove_expired(&mut self) {
+        self.cookies.retain(|cookie| !cookie.is_expired());
+    }
+
+    /// Serialize the cookies that should outlive this session.
+    ///
+    /// Expired cookies and session cookies (those without `Expires` or `Max-Age`) are left out,
+    /// as a browser would forget them once restarted.
+    /// To keep session cookies too, serialize [`Self::cookies`] yourself.
+    ///
+    /// Requires the `json` feature to be enabled.
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        let persistent: Vec<&Cookie> = self
+            .cookies
+            .iter()
+            .filter(|cookie| cookie.expires.is_some() && !cookie.is_expired())
+            .collect();
+        serde_json::to_string_pretty(&persistent)
+    }
+
+    /// Deserialize cookies serialized with [`Self::to_json`], dropping those that have expired since.
+    ///
+    /// Requires the `json` feature to be enabled.
+    #[cfg(feature = "json")]
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        let mut jar = Self {
+            cookies: serde_json::from_str(json)?,
+        };
+        jar.remove_expired();
+        Ok(jar)
+    }
+
+    /// Save the jar as a JSON file, see [`Self::to_json`].
+    ///
+    /// ```
+    /// use ehttp::cookies::CookieJar;
+    ///
+    /// let url = "https://www.example.com/";
+    /// let response = ehttp::Response::new(200, vec![])
+    ///     .with_header("Set-Cookie", "login=abc123; Max-Age=3600; Secure")
+    ///     .with_header("Set-Cookie", "flash=welcome")
+    ///     .with_header("Set-Cookie", "stale=1; Expires=Sun, 06 Nov 1994 08:49:37 GMT");
+    /// let mut jar = CookieJar::default();
+    /// jar.update_from_response(&response, url);
+    ///
+    /// let path = std::env::temp_dir().join("ehttp_cookie_jar_doctest.json");
+    /// jar.save(&path).unwrap();
+    ///
+    /// // Next run:
+    /// let jar = CookieJar::load(&path).unwrap();
+    /// assert_eq!(jar.cookie_header(url).as_deref(), Some("login=abc123"));
+    /// assert!(jar.cookies[0].secure);
+    /// ```
+    ///
+    /// Requires the `json` feature to be enabled.
+    #[cfg(feature = "json")]
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> crate::Result<()> {
+        let path = path.as_ref();
+        let json = self.to_json().map_err(|err| err.to_string())?;
+        std::fs::write(path, json).map_err(|err| format!("Failed to write {path:?}: {err}"))
+    }
+
+    /// Load a jar from a JSON file created with [`Self::save`], dropping expired cookies.
+    ///
+    /// Requires the `json` feature to be enabled.
+    #[cfg(feature = "json")]
+    pub fn load(path: impl AsRef<std::path::Path>) -> crate::Result<Self> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path)
+            .map_err(|err| format!("Failed to read {path:?}: {err}"))?;
+        Self::from_json(&json).map_err(|err| format!("Failed to parse {path:?}: {err}"))
+    }
+
+    /// The value for the `Cookie` header of a request to the given url,
+    /// or `None` if no cookies should be sent to it.
+    ///
+    /// Cookies with longer paths are listed first.
+    pub fn cookie_header(&self, url: &str) -> Option<String> {
+        let url = url::Url::parse(url).ok()?;
+        let mut cookies: Vec<&Cookie> = self.cookies.iter().filter(|c| c.matches(&url)).collect();
+        if cookies.is_empty() {
+            return None;
+        }
+        cookies.sort_by_key(|cookie| std::cmp::Reverse(cookie.path.len()));
+        let pairs: Vec<String> = cookies
+            .iter()
+            .map(|cookie| format!("{}={}", cookie.name, cookie.value))
+            .collect();
+        Some(pairs.join("; "))
+    }
+}
+
+/// The directory of the request path, used when a cookie has no `Path`.
+fn default_path(path: &str) -> String {
+    match path.rfind('/') {
+        Some(0) | None => "/".to_owned(),
+        Some(i) => path[..i].to_owned(),
+    }
+}
+
+fn domain_matches(host: &str, domain: &str) -> bool {
+    host == domain || (host.ends_with(domain) && host[..host.len() - domain.len()].ends_with('.'))
+}
+
+fn path_matches(request_path: &str, cookie_path: &str) -> bool {
+    request_path == cookie_path
+        || (request_path.starts_with(cookie_path)
+            && (cookie_path.ends_with('/') || request_path[cookie_path.len()..].starts_with('/')))
+}
+
+/// Parse a date like `Sun, 06 Nov 1994 08:49:37 GMT`, as used by `Expires`.
+///
+/// Like browsers, this is lenient, and also accepts the obsolete
+/// `Sunday, 06-Nov-94 08:49:37 GMT` and `Sun Nov  6 08:49:37 1994` formats.
+fn parse_http_date(date: &str) -> Option<SystemTime> {
+    const MONTHS: [&str; 12] = [
+        "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+    ];
+
+    let mut time = None;
+    let mut day = None;
+    let mut month = None;
+    let mut year = None;
+
+    for token in date
+        .split(|c: char| !c.is_ascii_alphanumeric() && c != ':')
+        .filter(|token| !token.is_empty())
+    {
+        if time.is_none() && token.contains(':') {
+            let mut parts = token.split(':').map(|part| part.parse::<u64>().ok());
+            if let (Some(Some(h)), Some(Some(m)), Some(Some(s))) =
+                (parts.next(), parts.next(), parts.next())
+            {
+                time = Some(h * 3600 + m * 60 + s);
+            }
+        } else if day.is_none() && token.len() <= 2 && token.bytes().all(|b| b.is_ascii_digit()) {
+            day = token.parse::<u64>().ok();
+        } else if month.is_none() && token.len() >= 3 {
+            let prefix = token[..3].to_ascii_lowercase();
+            if let Some(i) = MONTHS.iter().position(|m| *m == prefix) {
+                month = Some(i as u64 + 1);
+                continue;
+            }
+            if year.is_none() && token.bytes().all(|b| b.is_ascii_digit()) {
+                year = token.parse::<u64>().ok();
+            }
+        } else if year.is_none() && token.bytes().all(|b| b.is_ascii_digit()) {
+            year = token.parse::<u64>().ok();
+        }
+    }
+
+    // Two-digit years, as in the RFC 850 format:
+    let year = match year? {
+        year @ 0..=69 => year + 2000,
+        year @ 70..=99 => year + 1900,
+        year => year,
+    };
+    let (month, day, time) = (month?, day?, time?);
+    if year < 1970 || !(1..=31).contains(&day) || 24 * 3600 <= time {
+        return None;
+    }
+
+    let seconds = days_since_epoch(year, month, day) * 24 * 3600 + time;
+    Some(SystemTime::UNIX_EPOCH + Duration::from_secs(seconds))
+}
+
+/// Days from 1970-01-01 to the given date (which must not be earlier).
+fn days_since_epoch(year: u64, month: u64, day: u64) -> u64 {
+    // http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let year_of_era = year - era * 400;
+    let month_from_march = (month + 9) % 12;
+    let day_of_year = (153 * month_from_march + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
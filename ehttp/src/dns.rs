@@ -0,0 +1,178 @@
+use std::collections::BTreeMap;
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs as _};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How to cache DNS lookups, see [`set_dns_cache`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DnsCache {
+    /// Look a host up again once its cached addresses are this old.
+    pub ttl: Duration,
+
+    /// Cache at most this many hosts. When full, the entry that expires first is dropped.
+    pub max_entries: usize,
+}
+
+impl Default for DnsCache {
+    fn default() -> Self {
+        Self {
+            ttl: Duration::from_secs(60),
+            max_entries: 256,
+        }
+    }
+}
+
+/// Looks up the addresses of a `host:port`, see [`set_dns_resolver`].
+pub type DnsResolver = Arc<dyn Fn(&str) -> std::io::Result<Vec<SocketAddr>> + Send + Sync>;
+
+struct State {
+    cache: Option<DnsCache>,
+    resolver: Option<DnsResolver>,
+
+    /// Lowercase host -> when it expires, and its addresses.
+    entries: BTreeMap<String, (Instant, Vec<IpAddr>)>,
+}
+
+static STATE: Mutex<State> = Mutex::new(State {
+    cache: None,
+    resolver: None,
+    entries: BTreeMap::new(),
+});
+
+/// Cache DNS lookups, so that repeated requests to the same host don't have to wait for the lookup.
+///
+/// This is a measurable win for e.g. polling an API. The cache is keyed by host name,
+/// and shared by all requests, across all threads. Failed lookups are not cached.
+/// Hosts that are IP addresses are not cached.
+///
+/// `None` (the default) means no caching. Changing the setting clears the cache.
+///
+/// ```
+/// # use std::io::{Read as _, Write as _};
+/// # use std::sync::atomic::{AtomicUsize, Ordering};
+/// # use std::sync::Arc;
+/// # let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+/// # let addr = listener.local_addr().unwrap();
+/// # std::thread::spawn(move || {
+/// #     for stream in listener.incoming() {
+/// #         let mut stream = stream.unwrap();
+/// #         let mut buf = [0; 1024];
+/// #         let _ = stream.read(&mut buf).unwrap();
+/// #         stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok").unwrap();
+/// #     }
+/// # });
+/// // A stub resolver that counts its lookups:
+/// let lookups = Arc::new(AtomicUsize::new(0));
+/// let counter = lookups.clone();
+/// ehttp::set_dns_resolver(Some(Arc::new(move |_host_and_port: &str| {
+///     counter.fetch_add(1, Ordering::SeqCst);
+///     Ok(vec![addr])
+/// })));
+///
+/// ehttp::set_dns_cache(Some(ehttp::DnsCache {
+///     ttl: std::time::Duration::from_secs(300),
+///     max_entries: 100,
+/// }));
+/// let url = format!("http://my-service.internal:{}/health", addr.port());
+/// ehttp::fetch_blocking(&ehttp::Request::get(&url))?;
+/// ehttp::fetch_blocking(&ehttp::Request::get(&url))?;
+/// assert_eq!(lookups.load(Ordering::SeqCst), 1, "The second request used the cached address");
+///
+/// ehttp::set_dns_cache(None);
+/// ehttp::fetch_blocking(&ehttp::Request::get(&url))?;
+/// assert_eq!(lookups.load(Ordering::SeqCst), 2);
+/// # Ok::<(), ehttp::Error>(())
+/// ```
+///
+/// Only available when compiling for native.
+pub fn set_dns_cache(cache: Option<DnsCache>) {
+    let mut state = STATE.lock().unwrap();
+    if state.cache != cache {
+        state.entries.clear();
+    }
+    state.cache = cache;
+}
+
+/// The setting of [`set_dns_cache`].
+///
+/// Only available when compiling for native.
+pub fn dns_cache() -> Option<DnsCache> {
+    STATE.lock().unwrap().cache
+}
+
+/// Forget all cached DNS lookups, e.g. after a host has moved.
+///
+/// Only available when compiling for native.
+pub fn clear_dns_cache() {
+    STATE.lock().unwrap().entries.clear();
+}
+
+/// Look up hosts with this instead of the system resolver, e.g. for tests or a custom DNS.
+///
+/// It is called with a `host:port`, and its results are cached according to [`set_dns_cache`].
+/// `None` (the default) means the system resolver.
+///
+/// Only available when compiling for native.
+pub fn set_dns_resolver(resolver: Option<DnsResolver>) {
+    let mut state = STATE.lock().unwrap();
+    state.resolver = resolver;
+    state.entries.clear();
+}
+
+/// Look up the addresses of a `host:port`, using the cache if it is enabled.
+pub(crate) fn resolve(host_and_port: &str) -> std::io::Result<Vec<SocketAddr>> {
+    let (cache, resolver) = {
+        let state = STATE.lock().unwrap();
+        (state.cache, state.resolver.clone())
+    };
+    let (Some(cache), Some((host, port))) = (cache, split_host(host_and_port)) else {
+        return lookup(resolver, host_and_port);
+    };
+
+    let now = Instant::now();
+    if let Some((expires, ips)) = STATE.lock().unwrap().entries.get(&host) {
+        if now < *expires {
+            return Ok(ips.iter().map(|&ip| SocketAddr::new(ip, port)).collect());
+        }
+    }
+
+    // Don't hold the lock during the lookup, which may take a while.
+    let addrs = lookup(resolver, host_and_port)?;
+
+    let mut state = STATE.lock().unwrap();
+    if state.cache == Some(cache) && !addrs.is_empty() && 0 < cache.max_entries {
+        let now = Instant::now();
+        state.entries.retain(|_, (expires, _)| now < *expires);
+        while cache.max_entries <= state.entries.len() {
+            let first_to_expire = state
+                .entries
+                .iter()
+                .min_by_key(|(_, (expires, _))| *expires)
+                .map(|(host, _)| host.clone());
+            if let Some(host) = first_to_expire {
+                state.entries.remove(&host);
+            }
+        }
+        let ips = addrs.iter().map(SocketAddr::ip).collect();
+        state.entries.insert(host, (now + cache.ttl, ips));
+    }
+    Ok(addrs)
+}
+
+fn lookup(resolver: Option<DnsResolver>, host_and_port: &str) -> std::io::Result<Vec<SocketAddr>> {
+    match resolver {
+        Some(resolver) => resolver(host_and_port),
+        None => Ok(host_and_port.to_socket_addrs()?.collect()),
+    }
+}
+
+/// The lowercase host and the port of a `host:port`, unless the host is an IP address.
+fn split_host(host_and_port: &str) -> Option<(String, u16)> {
+    let (host, port) = host_and_port.rsplit_once(':')?;
+    let port = port.parse().ok()?;
+    let ip = host.trim_start_matches('[').trim_end_matches(']');
+    if ip.parse::<IpAddr>().is_ok() {
+        return None;
+    }
+    Some((host.to_ascii_lowercase(), port))
+}
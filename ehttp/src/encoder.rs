@@ -0,0 +1,149 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+/// Serializes a value into a request body, for [`crate::Request::body_with`].
+///
+/// Register one for a mime type with [`register_body_encoder`].
+/// Any `Fn(&dyn erased_serde::Serialize) -> ehttp::Result<Vec<u8>>` is an encoder.
+///
+/// Requires the `encoders` feature to be enabled.
+pub trait BodyEncoder: Send + Sync {
+    fn encode(&self, value: &dyn erased_serde::Serialize) -> crate::Result<Vec<u8>>;
+}
+
+impl<F> BodyEncoder for F
+where
+    F: Fn(&dyn erased_serde::Serialize) -> crate::Result<Vec<u8>> + Send + Sync,
+{
+    fn encode(&self, value: &dyn erased_serde::Serialize) -> crate::Result<Vec<u8>> {
+        self(value)
+    }
+}
+
+/// Encodes the body as JSON.
+///
+/// Used for `application/json`, and any `+json` mime type like `application/problem+json`,
+/// unless you register an encoder of your own for them.
+///
+/// Requires the `encoders` and `json` features to be enabled.
+#[cfg(feature = "json")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct JsonEncoder;
+
+#[cfg(feature = "json")]
+impl BodyEncoder for JsonEncoder {
+    fn encode(&self, value: &dyn erased_serde::Serialize) -> crate::Result<Vec<u8>> {
+        serde_json::to_vec(value).map_err(|err| format!("Failed to encode JSON: {err}"))
+    }
+}
+
+/// Encodes the body as MessagePack, like [`crate::Request::msgpack`].
+///
+/// Used for `application/msgpack`, `application/x-msgpack` and `application/vnd.msgpack`,
+/// unless you register an encoder of your own for them.
+///
+/// Requires the `encoders` and `msgpack` features to be enabled.
+#[cfg(feature = "msgpack")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MsgpackEncoder;
+
+#[cfg(feature = "msgpack")]
+impl BodyEncoder for MsgpackEncoder {
+    fn encode(&self, value: &dyn erased_serde::Serialize) -> crate::Result<Vec<u8>> {
+        rmp_serde::to_vec_named(value).map_err(|err| format!("Failed to encode MessagePack: {err}"))
+    }
+}
+
+/// Encodes the body as CBOR, like [`crate::Request::cbor`].
+///
+/// Used for `application/cbor`, and any `+cbor` mime type like `application/senml+cbor`,
+/// unless you register an encoder of your own for them.
+///
+/// Requires the `encoders` and `cbor` features to be enabled.
+#[cfg(feature = "cbor")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CborEncoder;
+
+#[cfg(feature = "cbor")]
+impl BodyEncoder for CborEncoder {
+    fn encode(&self, value: &dyn erased_serde::Serialize) -> crate::Result<Vec<u8>> {
+        let mut bytes = vec![];
+        ciborium::into_writer(value, &mut bytes)
+            .map_err(|err| format!("Failed to encode CBOR: {err}"))?;
+        Ok(bytes)
+    }
+}
+
+/// Lowercase mime type (without parameters) -> encoder.
+static ENCODERS: Mutex<BTreeMap<String, Arc<dyn BodyEncoder>>> = Mutex::new(BTreeMap::new());
+
+/// Use this encoder for bodies of the given mime type in [`crate::Request::body_with`],
+/// replacing any encoder previously registered for it.
+///
+/// The parameters of the mime type, like `; charset=utf-8`, are ignored, and so is its case.
+///
+/// Example, plugging in MessagePack with the `rmp-serde` crate:
+/// ```
+/// # mod rmp_serde {
+/// #     pub fn to_vec<T: serde::Serialize + ?Sized>(_value: &T) -> Result<Vec<u8>, String> { Ok(vec![0x80]) }
+/// # }
+/// ehttp::register_body_encoder(
+///     "application/msgpack",
+///     |value: &dyn ehttp::erased_serde::Serialize| -> ehttp::Result<Vec<u8>> {
+///         rmp_serde::to_vec(value).map_err(|err| err.to_string())
+///     },
+/// );
+///
+/// let mut settings = std::collections::BTreeMap::new();
+/// settings.insert("volume", 11);
+/// let request = ehttp::Request::post("https://www.example.com/settings", vec![])
+///     .body_with("application/msgpack", &settings)?;
+/// assert_eq!(request.headers.get("content-type"), Some("application/msgpack"));
+/// assert_eq!(request.body, [0x80]);
+/// # Ok::<(), ehttp::Error>(())
+/// ```
+///
+/// Requires the `encoders` feature to be enabled.
+pub fn register_body_encoder(mime: &str, encoder: impl BodyEncoder + 'static) {
+    ENCODERS
+        .lock()
+        .unwrap()
+        .insert(essence(mime), Arc::new(encoder));
+}
+
+/// The encoder for the given mime type: the registered one, or else a built-in one.
+pub(crate) fn body_encoder(mime: &str) -> Option<Arc<dyn BodyEncoder>> {
+    let mime = essence(mime);
+    if let Some(encoder) = ENCODERS.lock().unwrap().get(&mime) {
+        return Some(encoder.clone());
+    }
+
+    #[cfg(feature = "json")]
+    if mime == "application/json" || mime.ends_with("+json") {
+        return Some(Arc::new(JsonEncoder));
+    }
+
+    #[cfg(feature = "msgpack")]
+    if matches!(
+        mime.as_str(),
+        "application/msgpack" | "application/x-msgpack" | "application/vnd.msgpack"
+    ) {
+        return Some(Arc::new(MsgpackEncoder));
+    }
+
+    #[cfg(feature = "cbor")]
+    if mime == "application/cbor" || mime.ends_with("+cbor") {
+        return Some(Arc::new(CborEncoder));
+    }
+
+    None
+}
+
+/// E.g. `application/json` for `Application/JSON; charset=utf-8`.
+fn essence(mime: &str) -> String {
+    mime.split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase()
+}
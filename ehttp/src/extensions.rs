@@ -0,0 +1,92 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// A map of values keyed by their type, for carrying your own metadata with a [`crate::Request`],
+/// like a request id or a correlation token.
+///
+/// Extensions are never sent, and are ignored by everything in `ehttp`.
+#[cfg_attr(
+    not(target_arch = "wasm32"),
+    doc = "They are cloned along with the request, e.g. for retries, redirects and [`crate::paginate()`],"
+)]
+#[cfg_attr(
+    target_arch = "wasm32",
+    doc = "They are cloned along with the request, e.g. for retries and redirects,"
+)]
+/// and are available to a [`crate::Request::sign_with`] signer.
+///
+/// ```
+/// #[derive(Clone, Debug, PartialEq)]
+/// struct RequestId(u64);
+///
+/// let request = ehttp::Request::get("https://www.example.com").with_extension(RequestId(42));
+/// assert_eq!(request.extensions.get::<RequestId>(), Some(&RequestId(42)));
+/// assert_eq!(request.extensions.get::<String>(), None);
+///
+/// let mut request = request.clone();
+/// assert_eq!(request.extensions.insert(RequestId(43)), Some(RequestId(42)));
+/// assert_eq!(request.extensions.remove::<RequestId>(), Some(RequestId(43)));
+/// assert!(request.extensions.is_empty());
+/// ```
+#[derive(Clone, Default)]
+pub struct Extensions {
+    map: HashMap<TypeId, Arc<dyn Any + Send + Sync>>,
+}
+
+impl Extensions {
+    /// Insert a value, returning the previous value of the same type, if any.
+    pub fn insert<T: Clone + Send + Sync + 'static>(&mut self, value: T) -> Option<T> {
+        self.map
+            .insert(TypeId::of::<T>(), Arc::new(value))
+            .and_then(unwrap_arc)
+    }
+
+    /// Get the value of the given type, if any.
+    pub fn get<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        self.map.get(&TypeId::of::<T>())?.downcast_ref()
+    }
+
+    /// Get a mutable reference to the value of the given type, if any.
+    ///
+    /// If the value is shared with a clone of the extensions, it is cloned first.
+    pub fn get_mut<T: Clone + Send + Sync + 'static>(&mut self) -> Option<&mut T> {
+        let value = self.map.get_mut(&TypeId::of::<T>())?;
+        if Arc::get_mut(value).is_none() {
+            let cloned: T = value.downcast_ref::<T>()?.clone();
+            *value = Arc::new(cloned);
+        }
+        Arc::get_mut(value)?.downcast_mut()
+    }
+
+    /// Remove the value of the given type, returning it if there was one.
+    pub fn remove<T: Clone + Send + Sync + 'static>(&mut self) -> Option<T> {
+        self.map.remove(&TypeId::of::<T>()).and_then(unwrap_arc)
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+}
+
+/// Take the value out of the `Arc`, cloning it if it is shared.
+fn unwrap_arc<T: Clone + Send + Sync + 'static>(value: Arc<dyn Any + Send + Sync>) -> Option<T> {
+    let value = value.downcast::<T>().ok()?;
+    Some(Arc::try_unwrap(value).unwrap_or_else(|value| (*value).clone()))
+}
+
+impl std::fmt::Debug for Extensions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Extensions")
+            .field("len", &self.map.len())
+            .finish_non_exhaustive()
+    }
+}
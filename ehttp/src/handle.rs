@@ -0,0 +1,247 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::{cell::RefCell, io::Read, net::TcpStream};
+
+use crate::{Request, Response};
+
+/// Performs an HTTP request in the background, and returns a handle to it.
+///
+/// The handle is a [`Future`] of the response, which can also be aborted.
+/// Unlike [`crate::fetch_async`], this is available on native without the `native-async` feature,
+/// and works with any executor.
+///
+/// ```no_run
+/// async fn fetch_with_fallback(
+///     primary: ehttp::Request,
+///     fallback: ehttp::Request,
+/// ) -> ehttp::Result<ehttp::Response> {
+///     // Start both requests at once:
+///     let primary = ehttp::spawn_fetch(primary);
+///     let fallback = ehttp::spawn_fetch(fallback);
+///
+///     match primary.await {
+///         Ok(response) if response.ok => {
+///             fallback.abort();
+///             Ok(response)
+///         }
+///         _ => fallback.await,
+///     }
+/// }
+/// ```
+pub fn spawn_fetch(request: Request) -> FetchHandle {
+    let state = Arc::new(Mutex::new(State::default()));
+    let handle = FetchHandle {
+        state: state.clone(),
+    };
+
+    #[cfg(not(target_arch = "wasm32"))]
+    std::thread::Builder::new()
+        .name("ehttp".to_owned())
+        .spawn(move || {
+            CURRENT.with(|current| *current.borrow_mut() = Some(state.clone()));
+            let result = crate::fetch_blocking(&request);
+            CURRENT.with(|current| *current.borrow_mut() = None);
+            finish(&state, result);
+        })
+        .expect("Failed to spawn ehttp thread");
+
+    #[cfg(target_arch = "wasm32")]
+    crate::fetch(request, move |result| finish(&state, result));
+
+    handle
+}
+
+fn finish(state: &Mutex<State>, result: crate::Result<Response>) {
+    let mut state = state.lock().unwrap();
+    #[cfg(not(target_arch = "wasm32"))]
+    state.sockets.clear(); // Let the connections close
+    if !state.aborted {
+        state.result = Some(result);
+    }
+    if let Some(waker) = state.waker.take() {
+        waker.wake();
+    }
+}
+
+#[derive(Default)]
+struct State {
+    result: Option<crate::Result<Response>>,
+    aborted: bool,
+    waker: Option<Waker>,
+
+    /// The connections of the request, to shut down if it is aborted.
+    #[cfg(not(target_arch = "wasm32"))]
+    sockets: Vec<TcpStream>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+thread_local! {
+    /// The state of the [`spawn_fetch`] whose request is being sent on this thread, if any.
+    static CURRENT: RefCell<Option<Arc<Mutex<State>>>> = RefCell::new(None);
+}
+
+/// Let [`FetchHandle::abort`] shut down this connection,
+/// if it is for a request of [`spawn_fetch`]. Fails if that has already been aborted.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn register_socket(socket: &TcpStream) -> crate::Result<()> {
+    CURRENT.with(|current| {
+        let Some(state) = &*current.borrow() else {
+            return Ok(());
+        };
+        let mut state = state.lock().unwrap();
+        if state.aborted {
+            return Err("The fetch was aborted".to_owned());
+        }
+        let socket = socket.try_clone().map_err(|err| err.to_string())?;
+        state.sockets.push(socket);
+        Ok(())
+    })
+}
+
+/// Make the body fail once the [`spawn_fetch`] it is for has been aborted,
+/// if it is for one.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn abortable(reader: crate::native::ResponseReader) -> crate::native::ResponseReader {
+    match CURRENT.with(|current| current.borrow().clone()) {
+        Some(state) => Box::new(AbortableReader { reader, state }),
+        None => reader,
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+struct AbortableReader {
+    reader: crate::native::ResponseReader,
+    state: Arc<Mutex<State>>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Read for AbortableReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.state.lock().unwrap().aborted {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Other,
+                "The fetch was aborted",
+            ));
+        }
+        self.reader.read(buf)
+    }
+}
+
+/// A request started with [`spawn_fetch`].
+///
+/// Await it to get the response.
+pub struct FetchHandle {
+    state: Arc<Mutex<State>>,
+}
+
+impl FetchHandle {
+    /// Stop the request, and stop waiting for its response.
+    ///
+    /// Awaiting the handle after this results in an error.
+    ///
+    /// On native with the `own-client` feature, the connection is shut down right away,
+    /// so the server sees it close and nothing more is downloaded.
+    /// With `ureq`, which owns the connection, the download only stops at the next read of the body.
+    /// On web the browser still finishes the request, and only its response is thrown away.
+    ///
+    /// ```
+    /// # use std::io::{Read as _, Write as _};
+    /// # use std::time::Duration;
+    /// # fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    /// #     struct Unpark(std::thread::Thread);
+    /// #     impl std::task::Wake for Unpark {
+    /// #         fn wake(self: std::sync::Arc<Self>) {
+    /// #             self.0.unpark();
+    /// #         }
+    /// #     }
+    /// #     let waker = std::sync::Arc::new(Unpark(std::thread::current())).into();
+    /// #     let mut cx = std::task::Context::from_waker(&waker);
+    /// #     let mut future = std::pin::pin!(future);
+    /// #     loop {
+    /// #         match future.as_mut().poll(&mut cx) {
+    /// #             std::task::Poll::Ready(output) => return output,
+    /// #             std::task::Poll::Pending => std::thread::park(),
+    /// #         }
+    /// #     }
+    /// # }
+    /// # let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    /// # let url = format!("http://{}/huge", listener.local_addr().unwrap());
+    /// let (sent_sender, sent_receiver) = std::sync::mpsc::channel();
+    /// let (aborted_sender, aborted_receiver) = std::sync::mpsc::channel();
+    /// let server = std::thread::spawn(move || {
+    ///     let (mut stream, _) = listener.accept().unwrap();
+    ///     let _ = stream.read(&mut [0; 1024]).unwrap();
+    ///     stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 1000000000\r\n\r\nthe start").unwrap();
+    ///     // Give the client time to read that, and block waiting for the rest:
+    ///     std::thread::sleep(Duration::from_millis(200));
+    ///     sent_sender.send(()).unwrap();
+    ///
+    ///     // Send more after the abort, which `ureq` reads before it notices the abort.
+    ///     // With `own-client` the connection is already shut down, so this may fail.
+    ///     aborted_receiver.recv().unwrap();
+    ///     let _ = stream.write_all(b"and some more");
+    ///
+    ///     // Wait for the client to close the connection, instead of reading all of the body:
+    ///     stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    ///     match stream.read(&mut [0; 1024]) {
+    ///         Ok(0) => true,
+    ///         Ok(_) => false,
+    ///         Err(err) => !matches!(err.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut),
+    ///     }
+    /// });
+    ///
+    /// let handle = ehttp::spawn_fetch(ehttp::Request::get(url));
+    /// sent_receiver.recv().unwrap();
+    /// handle.abort();
+    /// aborted_sender.send(()).unwrap();
+    ///
+    /// assert_eq!(block_on(handle).unwrap_err(), "The fetch was aborted");
+    /// assert!(server.join().unwrap(), "The server should see the connection close");
+    /// ```
+    pub fn abort(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.aborted = true;
+        state.result = None;
+        #[cfg(not(target_arch = "wasm32"))]
+        for socket in state.sockets.drain(..) {
+            socket.shutdown(std::net::Shutdown::Both).ok();
+        }
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    }
+
+    /// Has the response arrived (or the fetch been aborted), so that awaiting won't block?
+    pub fn is_finished(&self) -> bool {
+        let state = self.state.lock().unwrap();
+        state.aborted || state.result.is_some()
+    }
+}
+
+impl Future for FetchHandle {
+    type Output = crate::Result<Response>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.state.lock().unwrap();
+        if state.aborted {
+            Poll::Ready(Err("The fetch was aborted".to_owned()))
+        } else if let Some(result) = state.result.take() {
+            Poll::Ready(result)
+        } else {
+            state.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+impl std::fmt::Debug for FetchHandle {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt.debug_struct("FetchHandle")
+            .field("finished", &self.is_finished())
+            .finish_non_exhaustive()
+    }
+}
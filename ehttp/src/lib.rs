@@ -11,7 +11,8 @@
 //! The given callback is called when the request is completed.
 //! You can communicate the results back to the main thread using something like:
 //!
-//! * Channels (e.g. [`std::sync::mpsc::channel`](https://doc.rust-lang.org/std/sync/mpsc/fn.channel.html)).
+//! * Channels (e.g. [`std::sync::mpsc::channel`](https://doc.rust-lang.org/std/sync/mpsc/fn.channel.html)),
+//!   which [`fetch_to_channel`] does for you.
 //! * `Arc<Mutex<_>>`
 //! * [`poll_promise::Promise`](https://docs.rs/poll-promise)
 //! * [`eventuals::Eventual`](https://docs.rs/eventuals/latest/eventuals/struct.Eventual.html)
@@ -44,6 +45,36 @@ pub fn fetch(request: Request, on_done: impl 'static + Send + FnOnce(Result<Resp
     web::fetch(request, Box::new(on_done));
 }
 
+/// Performs an HTTP request and sends the result on the given channel when done.
+///
+/// `wake` is called right after the result is sent (unless the receiver is gone),
+/// e.g. to wake up your UI so it checks the channel, with `egui::Context::request_repaint`.
+/// Just like the callback of [`fetch`] it is called from whatever thread finished the request.
+///
+/// ```
+/// let (sender, receiver) = std::sync::mpsc::channel();
+/// let (wake_sender, wake_receiver) = std::sync::mpsc::channel();
+///
+/// let request = ehttp::Request::get("data:,hello");
+/// ehttp::fetch_to_channel(request, sender, move || wake_sender.send(()).unwrap());
+///
+/// // In a UI you would wake up from `wake`, and then `try_recv` each frame.
+/// wake_receiver.recv().unwrap();
+/// let response = receiver.try_recv().unwrap().unwrap();
+/// assert_eq!(response.text(), Some("hello"));
+/// ```
+pub fn fetch_to_channel(
+    request: Request,
+    sender: std::sync::mpsc::Sender<Result<Response>>,
+    wake: impl 'static + Send + FnOnce(),
+) {
+    fetch(request, move |result| {
+        if sender.send(result).is_ok() {
+            wake();
+        }
+    });
+}
+
 /// Performs an `async` HTTP request.
 ///
 /// Available on following platforms:
@@ -72,13 +103,150 @@ pub async fn fetch_async(request: Request) -> Result<Response> {
     return web::fetch_async(&request).await;
 }
 
+/// Performs a blocking `GET` request, for quick scripts.
+///
+/// Short for [`fetch_blocking`]`(&`[`Request::get`]`(url))`.
+///
+/// ```
+/// let response = ehttp::get("data:,hello")?;
+/// assert_eq!(response.text(), Some("hello"));
+/// # Ok::<(), ehttp::Error>(())
+/// ```
+///
+/// Only available when compiling for native.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn get(url: impl ToString) -> Result<Response> {
+    fetch_blocking(&Request::get(url))
+}
+
+/// Performs a blocking `POST` request with `body` as JSON, for quick scripts.
+///
+/// Short for [`fetch_blocking`]`(&`[`Request::json`]`(url, body)?)`.
+///
+/// ```
+/// # use std::io::{Read as _, Write as _};
+/// # let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+/// # let url = format!("http://{}/echo", listener.local_addr().unwrap());
+/// # std::thread::spawn(move || {
+/// #     let (mut stream, _) = listener.accept().unwrap();
+/// #     let mut request = vec![];
+/// #     while !request.ends_with(br#"{"name":"ehttp"}"#) {
+/// #         let mut buf = [0; 1024];
+/// #         let n = stream.read(&mut buf).unwrap();
+/// #         request.extend_from_slice(&buf[..n]);
+/// #     }
+/// #     stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 16\r\n\r\n{\"name\":\"ehttp\"}").unwrap();
+/// # });
+/// let mut body = std::collections::BTreeMap::new();
+/// body.insert("name", "ehttp");
+/// let response = ehttp::post_json(url, &body)?;
+/// assert_eq!(response.text(), Some(r#"{"name":"ehttp"}"#));
+/// # Ok::<(), ehttp::Error>(())
+/// ```
+///
+/// Only available when compiling for native.
+/// Requires the `json` feature to be enabled.
+#[cfg(all(feature = "json", not(target_arch = "wasm32")))]
+pub fn post_json<T>(url: impl ToString, body: &T) -> Result<Response>
+where
+    T: ?Sized + serde::Serialize,
+{
+    let request =
+        Request::json(url, body).map_err(|err| format!("Failed to encode JSON: {err}"))?;
+    fetch_blocking(&request)
+}
+
+mod auth;
+pub use auth::{parse_authorization, AuthScheme};
+
+mod build_info;
+pub use build_info::{build_info, BuildInfo};
+
+#[cfg(feature = "encoders")]
+mod encoder;
+#[cfg(all(feature = "encoders", feature = "cbor"))]
+pub use encoder::CborEncoder;
+#[cfg(all(feature = "encoders", feature = "json"))]
+pub use encoder::JsonEncoder;
+#[cfg(all(feature = "encoders", feature = "msgpack"))]
+pub use encoder::MsgpackEncoder;
+#[cfg(feature = "encoders")]
+pub use encoder::{register_body_encoder, BodyEncoder};
+#[cfg(feature = "encoders")]
+pub use erased_serde;
+
+mod extensions;
+pub use extensions::Extensions;
+
+mod handle;
+pub use handle::{spawn_fetch, FetchHandle};
+
+mod link;
+
+#[cfg(any(not(target_arch = "wasm32"), feature = "streaming"))]
+mod paginate;
+#[cfg(not(target_arch = "wasm32"))]
+pub use paginate::paginate;
+#[cfg(all(
+    feature = "streaming",
+    any(target_arch = "wasm32", feature = "native-async")
+))]
+pub use paginate::paginate_async;
+
+mod method;
+pub use method::Method;
+
 mod types;
-pub use types::{Error, Headers, PartialResponse, Request, Response, Result};
+pub use types::{
+    default_timeout, https_only, set_default_timeout, set_https_only, timeout_kind, ContentRange,
+    Error, Headers, HttpVersion, PartialResponse, ProgressCallback, ProgressCallbacks, RateLimit,
+    RateLimitReset, Request, RequestTemplate, Response, Result, Signer, TimeoutKind,
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use types::{ContentLengthMismatch, DecompressionLimits, Proxy, RedirectPolicy, StatusRetry};
+
+#[cfg(not(target_arch = "wasm32"))]
+mod dns;
+#[cfg(not(target_arch = "wasm32"))]
+pub use dns::{clear_dns_cache, dns_cache, set_dns_cache, set_dns_resolver, DnsCache, DnsResolver};
+
+#[cfg(not(target_arch = "wasm32"))]
+mod limit;
+#[cfg(not(target_arch = "wasm32"))]
+pub use limit::{concurrency_limit, set_concurrency_limit, ConcurrencyLimit};
+
+#[cfg(all(
+    not(target_arch = "wasm32"),
+    not(feature = "ureq"),
+    not(feature = "own-client")
+))]
+compile_error!(
+    "ehttp needs the `ureq` feature (on by default) or the `own-client` feature on native"
+);
 
 #[cfg(not(target_arch = "wasm32"))]
 mod native;
 #[cfg(not(target_arch = "wasm32"))]
-pub use native::fetch_blocking;
+pub use native::{download_resumable, fetch_blocking, fetch_blocking_timeout};
+
+#[cfg(all(feature = "own-client", not(target_arch = "wasm32")))]
+mod own_client;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod no_proxy;
+#[cfg(not(target_arch = "wasm32"))]
+pub use no_proxy::NoProxy;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod tunnel;
+#[cfg(not(target_arch = "wasm32"))]
+pub use tunnel::{connect_tunnel, TunnelStream};
+
+#[cfg(all(feature = "own-client", not(target_arch = "wasm32")))]
+mod upgrade;
+#[cfg(all(feature = "own-client", not(target_arch = "wasm32")))]
+pub use upgrade::{upgrade, UpgradedStream};
 
 #[cfg(target_arch = "wasm32")]
 mod web;
@@ -91,7 +259,38 @@ pub mod streaming;
 #[cfg(feature = "multipart")]
 pub mod multipart;
 
+#[cfg(feature = "checksum")]
+pub mod checksum;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod cookies;
+
+#[cfg(feature = "json")]
+pub mod recording;
+
 #[deprecated = "Use ehttp::Headers::new"]
 pub fn headers(headers: &[(&str, &str)]) -> Headers {
     Headers::new(headers)
 }
+
+/// Build [`Headers`] from `key => value` pairs.
+///
+/// Both keys and values can be any expression implementing [`ToString`].
+///
+/// ```
+/// let token = format!("Bearer {}", "secret");
+/// let headers = ehttp::headers! {
+///     "Accept" => "*/*",
+///     "Authorization" => token,
+/// };
+/// assert_eq!(headers.get("authorization"), Some("Bearer secret"));
+/// ```
+#[macro_export]
+macro_rules! headers {
+    ($($key:expr => $value:expr),* $(,)?) => {{
+        #[allow(unused_mut)]
+        let mut headers = $crate::Headers::default();
+        $(headers.insert($key, $value);)*
+        headers
+    }};
+}
@@ -0,0 +1,102 @@
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/// A limit on how many requests can be in flight at once, see [`set_concurrency_limit`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ConcurrencyLimit {
+    /// At most this many requests are in flight at once. More requests wait for one of them to finish.
+    pub max_requests: usize,
+
+    /// Fail a request that has waited this long for its turn.
+    ///
+    /// `None` means waiting as long as it takes.
+    pub queue_timeout: Option<Duration>,
+}
+
+struct State {
+    limit: Option<ConcurrencyLimit>,
+    in_flight: usize,
+}
+
+static STATE: Mutex<State> = Mutex::new(State {
+    limit: None,
+    in_flight: 0,
+});
+static FINISHED: Condvar = Condvar::new();
+
+/// Limit how many requests can be in flight at once, across all threads.
+///
+/// This protects e.g. a server making requests on behalf of its users from running out of
+/// sockets and file descriptors. Requests over the limit wait for their turn, in the thread sending them
+/// (which for [`crate::fetch`] is a thread of its own).
+///
+/// A request is in flight from when it is sent until its whole response has been received,
+/// or until a [`crate::streaming`] fetch of it is aborted.
+/// `file://` and `data:` urls are not limited.
+///
+/// `None` (the default) means no limit.
+///
+/// ```
+/// use std::time::Duration;
+/// ehttp::set_concurrency_limit(Some(ehttp::ConcurrencyLimit {
+///     max_requests: 64,
+///     queue_timeout: Some(Duration::from_secs(10)),
+/// }));
+/// assert_eq!(ehttp::concurrency_limit().unwrap().max_requests, 64);
+/// # ehttp::set_concurrency_limit(None);
+/// ```
+///
+/// Only available when compiling for native.
+pub fn set_concurrency_limit(limit: Option<ConcurrencyLimit>) {
+    STATE.lock().unwrap().limit = limit;
+    FINISHED.notify_all(); // A higher limit may let waiting requests through
+}
+
+/// The limit set with [`set_concurrency_limit`].
+///
+/// Only available when compiling for native.
+pub fn concurrency_limit() -> Option<ConcurrencyLimit> {
+    STATE.lock().unwrap().limit
+}
+
+/// Counts as one of the requests in flight until dropped.
+pub(crate) struct Permit(());
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        STATE.lock().unwrap().in_flight -= 1;
+        FINISHED.notify_all();
+    }
+}
+
+/// Wait for our turn to send a request, if there is a [`ConcurrencyLimit`].
+pub(crate) fn acquire() -> crate::Result<Option<Permit>> {
+    let mut state = STATE.lock().unwrap();
+    let Some(limit) = state.limit else {
+        return Ok(None);
+    };
+    let deadline = limit.queue_timeout.map(|timeout| Instant::now() + timeout);
+
+    loop {
+        match state.limit {
+            Some(limit) if limit.max_requests <= state.in_flight => {}
+            _ => break,
+        }
+        state = match deadline {
+            Some(deadline) => {
+                let now = Instant::now();
+                if deadline <= now {
+                    return Err(format!(
+                        "Timed out waiting for another request to finish, with {} in flight",
+                        state.in_flight
+                    ));
+                }
+                FINISHED.wait_timeout(state, deadline - now).unwrap().0
+            }
+            None => FINISHED.wait(state).unwrap(),
+        };
+    }
+
+    state.in_flight += 1;
+    Ok(Some(Permit(())))
+}
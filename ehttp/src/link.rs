@@ -0,0 +1,72 @@
+/// Parse the value of a `Link` header ([RFC 8288](https://www.rfc-editor.org/rfc/rfc8288))
+/// into `(rel, target)` pairs, e.g. `("next", "https://api.example.com/items?page=2")`.
+///
+/// A link with several relations, like `rel="next last"`, gives one pair per relation.
+/// The relations are lowercased. Links without a `rel` are skipped.
+pub(crate) fn parse_links(value: &str) -> Vec<(String, String)> {
+    let mut links = vec![];
+    let mut rest = value;
+
+    while let Some(start) = rest.find('<') {
+        let Some(end) = rest[start..].find('>') else {
+            break;
+        };
+        let target = rest[start + 1..start + end].trim();
+        rest = &rest[start + end + 1..];
+
+        // The parameters run until the next comma outside of a quoted string:
+        let mut in_quotes = false;
+        let params_end = rest
+            .char_indices()
+            .find(|&(_, c)| {
+                if c == '"' {
+                    in_quotes = !in_quotes;
+                }
+                c == ',' && !in_quotes
+            })
+            .map_or(rest.len(), |(i, _)| i);
+        let params = &rest[..params_end];
+        rest = &rest[params_end..];
+
+        for param in params.split(';') {
+            let Some((name, value)) = param.split_once('=') else {
+                continue;
+            };
+            if name.trim().eq_ignore_ascii_case("rel") {
+                let value = value.trim().trim_matches('"');
+                for rel in value.split_whitespace() {
+                    links.push((rel.to_ascii_lowercase(), target.to_owned()));
+                }
+            }
+        }
+    }
+
+    links
+}
+
+/// Resolve a possibly relative url reference, like the target of a link, against the url it came from.
+pub(crate) fn resolve(base: &str, reference: &str) -> String {
+    if reference.contains("://") {
+        return reference.to_owned();
+    }
+    let Some((scheme, rest)) = base.split_once("://") else {
+        return reference.to_owned();
+    };
+    if let Some(network_path) = reference.strip_prefix("//") {
+        return format!("{scheme}://{network_path}");
+    }
+
+    let authority_end = rest.find(|c| c == '/' || c == '?' || c == '#');
+    let (authority, path) = rest.split_at(authority_end.unwrap_or(rest.len()));
+    let path = &path[..path.find(|c| c == '?' || c == '#').unwrap_or(path.len())];
+
+    if reference.starts_with('/') {
+        format!("{scheme}://{authority}{reference}")
+    } else if reference.starts_with('?') {
+        format!("{scheme}://{authority}{path}{reference}")
+    } else {
+        let directory = &path[..path.rfind('/').map_or(0, |i| i + 1)];
+        let directory = if directory.is_empty() { "/" } else { directory };
+        format!("{scheme}://{authority}{directory}{reference}")
+    }
+}
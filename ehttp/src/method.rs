@@ -0,0 +1,146 @@
+/// An HTTP request method, e.g. `GET` or `POST`.
+///
+/// Extension methods, like WebDAV's `PROPFIND` or `PURGE` for cache invalidation,
+/// are represented by [`Method::Other`]. Use [`Method::custom`] or `parse` to create one,
+/// so that the verb is validated.
+///
+/// ```
+/// use ehttp::Method;
+/// assert_eq!("GET".parse::<Method>(), Ok(Method::Get));
+/// assert_eq!("PROPFIND".parse::<Method>(), Ok(Method::Other("PROPFIND".to_owned())));
+/// assert_eq!(Method::custom("MKCOL").unwrap().to_string(), "MKCOL");
+/// assert!(Method::custom("NOT VALID").is_err());
+/// assert!(Method::custom("").is_err());
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Method {
+    Get,
+    Head,
+    Post,
+    Put,
+    Delete,
+    Connect,
+    Options,
+    Trace,
+    Patch,
+
+    /// Any other method, e.g. `PROPFIND`.
+    Other(String),
+}
+
+impl Method {
+    /// All the standard methods, i.e. all but [`Method::Other`].
+    ///
+    /// ```
+    /// for method in ehttp::Method::ALL {
+    ///     assert_eq!(method.as_str().parse(), Ok(method));
+    /// }
+    /// ```
+    pub const ALL: [Self; 9] = [
+        Self::Get,
+        Self::Head,
+        Self::Post,
+        Self::Put,
+        Self::Delete,
+        Self::Connect,
+        Self::Options,
+        Self::Trace,
+        Self::Patch,
+    ];
+
+    /// Create a method from the given verb.
+    ///
+    /// Fails if the verb is not a valid HTTP token.
+    pub fn custom(verb: &str) -> crate::Result<Self> {
+        verb.parse()
+    }
+
+    /// The verb, as sent on the wire.
+    ///
+    /// Unlike `to_string`, this doesn't allocate.
+    ///
+    /// This borrows from `self` rather than being `&'static str`, because the verb of
+    /// [`Method::Other`] is a `String` of its own. Use [`Self::as_static_str`] for a `&'static str`
+    /// of the standard methods.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Other(verb) => verb,
+            method => method.as_static_str().unwrap_or_default(),
+        }
+    }
+
+    /// The verb of a standard method, or `None` for [`Method::Other`].
+    ///
+    /// ```
+    /// assert_eq!(ehttp::Method::Get.as_static_str(), Some("GET"));
+    /// assert_eq!(ehttp::Method::custom("PROPFIND").unwrap().as_static_str(), None);
+    /// ```
+    pub fn as_static_str(&self) -> Option<&'static str> {
+        Some(match self {
+            Self::Get => "GET",
+            Self::Head => "HEAD",
+            Self::Post => "POST",
+            Self::Put => "PUT",
+            Self::Delete => "DELETE",
+            Self::Connect => "CONNECT",
+            Self::Options => "OPTIONS",
+            Self::Trace => "TRACE",
+            Self::Patch => "PATCH",
+            Self::Other(_) => return None,
+        })
+    }
+}
+
+/// Is this a valid HTTP token, as defined by RFC 9110?
+pub(crate) fn is_token(s: &str) -> bool {
+    !s.is_empty()
+        && s.bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b))
+}
+
+impl std::str::FromStr for Method {
+    type Err = crate::Error;
+
+    /// Methods are case-sensitive, so `get` becomes [`Method::Other`].
+    fn from_str(s: &str) -> crate::Result<Self> {
+        Ok(match s {
+            "GET" => Self::Get,
+            "HEAD" => Self::Head,
+            "POST" => Self::Post,
+            "PUT" => Self::Put,
+            "DELETE" => Self::Delete,
+            "CONNECT" => Self::Connect,
+            "OPTIONS" => Self::Options,
+            "TRACE" => Self::Trace,
+            "PATCH" => Self::Patch,
+            _ if is_token(s) => Self::Other(s.to_owned()),
+            _ => return Err(format!("Invalid HTTP method: {s:?}")),
+        })
+    }
+}
+
+impl From<Method> for String {
+    /// Reuses the verb of [`Method::Other`] instead of allocating a new one.
+    fn from(method: Method) -> Self {
+        match method {
+            Method::Other(verb) => verb,
+            method => method.as_str().to_owned(),
+        }
+    }
+}
+
+impl From<Method> for std::borrow::Cow<'static, str> {
+    /// Only allocates for [`Method::Other`], whose verb is reused.
+    fn from(method: Method) -> Self {
+        match method {
+            Method::Other(verb) => Self::Owned(verb),
+            method => Self::Borrowed(method.as_static_str().unwrap_or_default()),
+        }
+    }
+}
+
+impl std::fmt::Display for Method {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
@@ -1,4 +1,4 @@
-//! Multipart HTTP request for both native and WASM.
+//! Multipart HTTP request for both native and WASM, and parsing of multipart responses.
 //!
 //! Requires the `multipart` feature to be enabled.
 //!
@@ -24,15 +24,17 @@
 //! Taken from ureq_multipart 1.1.1
 //!
 
+use crate::Headers;
 use mime::Mime;
 use rand::Rng;
 
-use std::fs::File;
 use std::io::{self, Read, Write};
-use std::path::Path;
+#[cfg(not(target_arch = "wasm32"))]
+use std::{fs::File, path::Path};
 
 const BOUNDARY_LEN: usize = 29;
 
+#[cfg(not(target_arch = "wasm32"))]
 fn opt_filename(path: &Path) -> Option<&str> {
     path.file_name().and_then(|filename| filename.to_str())
 }
@@ -45,6 +47,7 @@ fn random_alphanumeric(len: usize) -> String {
         .collect()
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 fn mime_filename(path: &Path) -> (Mime, Option<&str>) {
     let content_type = mime_guess::from_path(path);
     let filename = opt_filename(path);
@@ -55,6 +58,7 @@ fn mime_filename(path: &Path) -> (Mime, Option<&str>) {
 /// The Builder for the multipart
 pub struct MultipartBuilder {
     boundary: String,
+    subtype: String,
     inner: Vec<u8>,
     data_written: bool,
 }
@@ -71,11 +75,31 @@ impl MultipartBuilder {
     pub fn new() -> Self {
         Self {
             boundary: random_alphanumeric(BOUNDARY_LEN),
+            subtype: "form-data".to_owned(),
             inner: Vec::new(),
             data_written: false,
         }
     }
 
+    /// Set the multipart subtype used in the content type, e.g. `mixed` or `related`.
+    ///
+    /// Defaults to `form-data`. The parts are framed the same way for all subtypes,
+    /// and still get a `form-data` `Content-Disposition` header,
+    /// which not all servers expect for other subtypes.
+    ///
+    /// ```
+    /// use ehttp::multipart::MultipartBuilder;
+    /// let (content_type, _) = MultipartBuilder::new()
+    ///     .subtype("mixed")
+    ///     .add_text("label", "lorem ipsum")
+    ///     .finish();
+    /// assert!(content_type.starts_with("multipart/mixed; boundary="));
+    /// ```
+    pub fn subtype(mut self, subtype: &str) -> Self {
+        self.subtype = subtype.to_owned();
+        self
+    }
+
     /// add text field
     ///
     /// * name field name
@@ -86,6 +110,22 @@ impl MultipartBuilder {
         self
     }
 
+    /// add several text fields, in the given order
+    ///
+    /// ```
+    /// use ehttp::multipart::MultipartBuilder;
+    /// let (_, data) = MultipartBuilder::new()
+    ///     .add_fields(&[("first", "1"), ("second", "2")])
+    ///     .finish();
+    /// let data = String::from_utf8(data).unwrap();
+    /// assert!(data.find("name=\"first\"").unwrap() < data.find("name=\"second\"").unwrap());
+    /// ```
+    pub fn add_fields(self, fields: &[(&str, &str)]) -> Self {
+        fields
+            .iter()
+            .fold(self, |builder, (name, text)| builder.add_text(name, text))
+    }
+
     /// add file
     ///
     /// * name file field name
@@ -171,10 +211,143 @@ impl MultipartBuilder {
         .unwrap();
         (
             format!(
-                "multipart/form-data; boundary=---------------------------{}",
-                self.boundary
+                "multipart/{}; boundary=---------------------------{}",
+                self.subtype, self.boundary
             ),
             self.inner,
         )
     }
+
+    /// Like [`Self::finish`], but returns the `Content-Type` and `Content-Length` headers
+    /// ready to be added to a [`crate::Request`].
+    ///
+    /// ```
+    /// use ehttp::multipart::MultipartBuilder;
+    /// let (headers, data) = MultipartBuilder::new()
+    ///     .add_text("label", "lorem ipsum")
+    ///     .finish_with_headers();
+    /// assert!(headers.get("Content-Type").unwrap().starts_with("multipart/form-data; boundary="));
+    /// assert_eq!(headers.get("Content-Length"), Some(data.len().to_string().as_str()));
+    /// ```
+    pub fn finish_with_headers(self) -> (Headers, Vec<u8>) {
+        let (content_type, data) = self.finish();
+        let mut headers = Headers::default();
+        headers.insert("Content-Type", content_type);
+        headers.insert("Content-Length", data.len());
+        (headers, data)
+    }
+}
+
+/// A part of a multipart body, see [`parse`].
+#[derive(Clone, Debug)]
+pub struct MultipartPart {
+    /// E.g. `Content-Type` and `Content-Disposition`.
+    pub headers: Headers,
+
+    /// The body of the part.
+    pub bytes: Vec<u8>,
+}
+
+/// Parse a multipart body of the given content type into its parts.
+///
+/// The boundary is read from the content type, e.g. `multipart/byteranges; boundary=XYZ`.
+/// The preamble before the first part and the epilogue after the closing boundary are ignored.
+///
+/// ```
+/// let body = b"preamble\r\n\
+///     --XYZ\r\n\
+///     Content-Type: text/plain\r\n\
+///     Content-Range: bytes 0-4/20\r\n\
+///     \r\n\
+///     hello\r\n\
+///     --XYZ\r\n\
+///     Content-Type: text/plain\r\n\
+///     \r\n\
+///     world\r\n\
+///     --XYZ--\r\n";
+/// let parts = ehttp::multipart::parse("multipart/byteranges; boundary=XYZ", body).unwrap();
+/// assert_eq!(parts.len(), 2);
+/// assert_eq!(parts[0].headers.get("content-range"), Some("bytes 0-4/20"));
+/// assert_eq!(parts[0].bytes, b"hello");
+/// assert_eq!(parts[1].bytes, b"world");
+///
+/// assert!(ehttp::multipart::parse("multipart/mixed; boundary=XYZ", b"--XYZ\r\n\r\nunterminated").is_err());
+/// ```
+pub fn parse(content_type: &str, body: &[u8]) -> crate::Result<Vec<MultipartPart>> {
+    let mime: Mime = content_type
+        .parse()
+        .map_err(|err| format!("Invalid content type {content_type:?}: {err}"))?;
+    if mime.type_() != mime::MULTIPART {
+        return Err(format!("Not a multipart content type: {content_type:?}"));
+    }
+    let boundary = mime
+        .get_param(mime::BOUNDARY)
+        .ok_or_else(|| format!("No boundary in the content type {content_type:?}"))?;
+    let delimiter = format!("--{boundary}").into_bytes();
+
+    let start = find(body, &delimiter).ok_or("No multipart boundary found in the body")?;
+    let mut rest = &body[start + delimiter.len()..];
+    let mut parts = vec![];
+    loop {
+        if rest.starts_with(b"--") {
+            return Ok(parts); // The closing boundary
+        }
+        // Skip any whitespace after the boundary, up to the end of the line:
+        let line_end = find(rest, b"\r\n").ok_or("Unterminated multipart boundary line")?;
+        rest = &rest[line_end + 2..];
+
+        let end = find_delimiter(rest, &delimiter).ok_or("Missing closing multipart boundary")?;
+        parts.push(parse_part(&rest[..end])?);
+        rest = &rest[end + 2 + delimiter.len()..];
+    }
+}
+
+/// Parse the multipart body of a response, using its `content-type` header.
+pub fn parse_response(response: &crate::Response) -> crate::Result<Vec<MultipartPart>> {
+    let content_type = response
+        .content_type()
+        .ok_or("The response has no content-type")?;
+    parse(content_type, &response.bytes)
+}
+
+fn parse_part(part: &[u8]) -> crate::Result<MultipartPart> {
+    let (head, bytes) = if let Some(bytes) = part.strip_prefix(b"\r\n") {
+        (&[][..], bytes) // No headers
+    } else {
+        let head_end = find(part, b"\r\n\r\n").ok_or("Multipart part without an end of headers")?;
+        (&part[..head_end], &part[head_end + 4..])
+    };
+
+    let head = std::str::from_utf8(head).map_err(|_| "Multipart part headers are not UTF-8")?;
+    let mut headers = Headers::default();
+    for line in head.split("\r\n").filter(|line| !line.is_empty()) {
+        let (key, value) = line
+            .split_once(':')
+            .ok_or_else(|| format!("Invalid multipart part header: {line:?}"))?;
+        headers.insert(key.trim(), value.trim());
+    }
+
+    Ok(MultipartPart {
+        headers,
+        bytes: bytes.to_vec(),
+    })
+}
+
+/// Find the `\r\n` followed by the delimiter that ends a part.
+fn find_delimiter(haystack: &[u8], delimiter: &[u8]) -> Option<usize> {
+    let mut offset = 0;
+    while let Some(i) = find(&haystack[offset..], b"\r\n") {
+        let i = offset + i;
+        if haystack[i + 2..].starts_with(delimiter) {
+            return Some(i);
+        }
+        offset = i + 2;
+    }
+    None
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
 }
@@ -1,3 +1,16 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::io::{BufRead, Read};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::types::{
+    percent_decode, percent_decode_bytes, split_userinfo, without_userinfo, PartialResponse,
+};
+use crate::types::{CONNECT_TIMEOUT, READ_TIMEOUT};
+use crate::HttpVersion;
+use crate::{ContentRange, DecompressionLimits, ProgressCallback};
 use crate::{Request, Response};
 
 #[cfg(feature = "native-async")]
@@ -9,6 +22,36 @@ use async_channel::{Receiver, Sender};
 ///
 /// NOTE: `Ok(…)` is returned on network error.
 ///
+/// `file://` urls are read from the local file system, with a `404` if the file doesn't exist.
+/// The content type is guessed from the extension with the `multipart` feature,
+/// and is `application/octet-stream` otherwise. Only `GET` and `HEAD` are supported for them.
+/// This works with all the native fetch functions, but not on web.
+///
+/// ```
+/// let path = std::env::temp_dir().join("ehttp_fetch_blocking_doctest.txt");
+/// std::fs::write(&path, "hello").unwrap();
+/// let response = ehttp::fetch_blocking(&ehttp::Request::get(format!("file://{}", path.display()))).unwrap();
+/// assert_eq!(response.bytes, b"hello");
+/// # #[cfg(feature = "multipart")]
+/// assert_eq!(response.content_type(), Some("text/plain"));
+///
+/// let missing = ehttp::fetch_blocking(&ehttp::Request::get("file:///does/not/exist")).unwrap();
+/// assert_eq!(missing.status, 404);
+/// ```
+///
+/// `data:` urls are decoded, both the base64 and the percent-encoded kind.
+/// On web the browser does this.
+///
+/// ```
+/// let response = ehttp::fetch_blocking(&ehttp::Request::get("data:text/plain;base64,aGVsbG8=")).unwrap();
+/// assert_eq!(response.bytes, b"hello");
+/// assert_eq!(response.content_type(), Some("text/plain"));
+///
+/// let response = ehttp::fetch_blocking(&ehttp::Request::get("data:,hello%20world")).unwrap();
+/// assert_eq!(response.bytes, b"hello world");
+/// assert_eq!(response.content_type(), Some("text/plain;charset=US-ASCII"));
+/// ```
+///
 /// `Ok` is returned if we get a response, even if it's a 404.
 ///
 /// `Err` can happen for a number of reasons:
@@ -22,56 +65,1194 @@ use async_channel::{Receiver, Sender};
 /// * The initial GET which returned HTML contained CSP headers to block access to the resource
 /// * A browser extension blocked the request (e.g. ad blocker)
 /// * …
+///
+/// Each request is sent on its own connection, so requests are never pipelined,
+/// and concurrent requests can never receive each other's responses:
+///
+/// ```
+/// # use std::io::{BufRead as _, Write as _};
+/// # let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+/// # let url = format!("http://{}", listener.local_addr().unwrap());
+/// # std::thread::spawn(move || {
+/// #     for stream in listener.incoming() {
+/// #         let stream = stream.unwrap();
+/// #         std::thread::spawn(move || {
+/// #             // Echo the id in the path back, after a random-ish delay to interleave the responses:
+/// #             let mut reader = std::io::BufReader::new(&stream);
+/// #             let mut line = String::new();
+/// #             reader.read_line(&mut line).unwrap();
+/// #             let id: u64 = line.split(' ').nth(1).unwrap().trim_start_matches('/').parse().unwrap();
+/// #             while line != "\r\n" {
+/// #                 line.clear();
+/// #                 reader.read_line(&mut line).unwrap();
+/// #             }
+/// #             std::thread::sleep(std::time::Duration::from_millis(id * 7 % 20));
+/// #             let body = id.to_string();
+/// #             write!(&stream, "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{body}", body.len()).unwrap();
+/// #         });
+/// #     }
+/// # });
+/// let threads: Vec<_> = (0..32)
+///     .map(|id| {
+///         let url = format!("{url}/{id}");
+///         std::thread::spawn(move || {
+///             let response = ehttp::fetch_blocking(&ehttp::Request::get(url)).unwrap();
+///             assert_eq!(response.text(), Some(id.to_string().as_str()));
+///         })
+///     })
+///     .collect();
+/// for thread in threads {
+///     thread.join().unwrap();
+/// }
+/// ```
+///
+/// A body that is cut off before its `Content-Length` is an error.
+/// So is a chunked body that is cut off before its last chunk, but only with the `own-client` feature.
+/// This is a known limitation of `ureq` (the default): it ends a cut-off chunked body as if it were complete,
+/// and doesn't let us tell the difference, so its truncated body is returned instead.
+///
+/// ```
+/// # use std::io::{Read as _, Write as _};
+/// fn fetch_cut_off(raw_response: &'static [u8]) -> ehttp::Result<ehttp::Response> {
+///     let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+///     let url = format!("http://{}/", listener.local_addr().unwrap());
+///     std::thread::spawn(move || {
+///         let (mut stream, _) = listener.accept().unwrap();
+///         let _ = stream.read(&mut [0; 1024]).unwrap();
+///         stream.write_all(raw_response).unwrap();
+///     });
+///     ehttp::fetch_blocking(&ehttp::Request::get(url))
+/// }
+///
+/// assert!(fetch_cut_off(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhel").is_err());
+///
+/// let chunked = fetch_cut_off(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhel");
+/// if cfg!(feature = "own-client") {
+///     assert!(chunked.is_err());
+/// } else {
+///     assert_eq!(chunked.unwrap().bytes, b"hel");
+/// }
+/// ```
 pub fn fetch_blocking(request: &Request) -> crate::Result<Response> {
-    let mut req = ureq::request(&request.method, &request.url);
+    let start = std::time::Instant::now();
+    let (response, mut reader, trailers) = send_with_trailers(request)?;
 
-    for (k, v) in &request.headers {
-        req = req.set(k, v);
+    let mut bytes = vec![];
+    let read = match request.truncate_body_at {
+        // One more byte, to know if there was more:
+        Some(max_bytes) => (&mut reader)
+            .take(max_bytes as u64 + 1)
+            .read_to_end(&mut bytes),
+        None => reader.read_to_end(&mut bytes),
+    };
+    if let Err(err) = read {
+        if request.method == "HEAD" && err.kind() == std::io::ErrorKind::UnexpectedEof {
+            // We don't really expect a body for HEAD requests, so this is fine.
+        } else {
+            return Err(body_read_error(&err));
+        }
+    }
+    let truncated = request.truncate_body(&mut bytes);
+
+    #[cfg(feature = "checksum")]
+    if let Some(expected) = request.expected_sha256.as_ref().filter(|_| !truncated) {
+        crate::checksum::verify_sha256(expected, &bytes)?;
     }
 
-    let resp = if request.body.is_empty() {
-        req.call()
+    let mut response = response.complete(bytes);
+    response.truncated = truncated;
+    response.trailers = std::mem::take(&mut *trailers.lock().unwrap());
+    response.elapsed = start.elapsed();
+    Ok(response)
+}
+
+/// The error for failing to read the body of a response.
+pub(crate) fn body_read_error(err: &std::io::Error) -> String {
+    let message = if cfg!(not(feature = "own-client"))
+        && err.to_string().contains("decoding chunks")
+    {
+        // ureq can't decode chunked bodies with trailers or bare `\n` line endings, but we can.
+        format!("Failed to read response body: {err} (the `own-client` feature of ehttp may help)")
     } else {
-        req.send_bytes(&request.body)
+        format!("Failed to read response body: {err}")
     };
+    timeout_error(READ_TIMEOUT, err, message)
+}
 
-    let (ok, resp) = match resp {
-        Ok(resp) => (true, resp),
-        Err(ureq::Error::Status(_, resp)) => (false, resp), // Still read the body on e.g. 404
-        Err(ureq::Error::Transport(err)) => return Err(err.to_string()),
+/// Start the message with `prefix` if `err` is a timeout, so [`crate::timeout_kind`] can tell.
+fn timeout_error(prefix: &str, err: &std::io::Error, message: String) -> String {
+    let is_timeout = matches!(
+        err.kind(),
+        std::io::ErrorKind::TimedOut | std::io::ErrorKind::WouldBlock // `WouldBlock` on some platforms
+    );
+    if is_timeout {
+        format!("{prefix}{message}")
+    } else {
+        message
+    }
+}
+
+/// The error for a `ureq` transport error, with the kind of timeout it was, if any.
+#[cfg(not(feature = "own-client"))]
+fn transport_error(err: &ureq::Transport) -> String {
+    let prefix = match err.kind() {
+        ureq::ErrorKind::ConnectionFailed => CONNECT_TIMEOUT,
+        ureq::ErrorKind::Io => READ_TIMEOUT,
+        _ => return err.to_string(),
     };
+    let io_err =
+        std::error::Error::source(err).and_then(|source| source.downcast_ref::<std::io::Error>());
+    match io_err {
+        Some(io_err) => timeout_error(prefix, io_err, err.to_string()),
+        None => err.to_string(),
+    }
+}
 
-    let url = resp.get_url().to_owned();
-    let status = resp.status();
-    let status_text = resp.status_text().to_owned();
-    let mut headers = crate::Headers::default();
-    for key in &resp.headers_names() {
-        if let Some(value) = resp.header(key) {
-            headers.insert(key, value.to_owned());
+/// Like [`fetch_blocking`], but gives up if the whole request takes longer than `timeout`.
+///
+/// This applies even if [`Request::timeout`] isn't set.
+/// If it is set, the shorter of the two is used.
+///
+/// A server that keeps sending a byte now and then still runs out of time:
+///
+/// ```
+/// # use std::io::{Read as _, Write as _};
+/// # use std::time::Duration;
+/// # let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+/// # let url = format!("http://{}", listener.local_addr().unwrap());
+/// # std::thread::spawn(move || {
+/// #     let (mut stream, _) = listener.accept().unwrap();
+/// #     let _ = stream.read(&mut [0; 1024]).unwrap();
+/// #     let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 100\r\n\r\n");
+/// #     for _ in 0..100 {
+/// #         std::thread::sleep(Duration::from_millis(20));
+/// #         if stream.write_all(b".").is_err() {
+/// #             break;
+/// #         }
+/// #     }
+/// # });
+/// let start = std::time::Instant::now();
+/// let error = ehttp::fetch_blocking_timeout(&ehttp::Request::get(url), Duration::from_millis(200)).unwrap_err();
+/// assert_eq!(ehttp::timeout_kind(&error), Some(ehttp::TimeoutKind::Read), "{}", error);
+/// assert!(start.elapsed() < Duration::from_secs(1));
+/// ```
+///
+/// Only available when compiling for native.
+pub fn fetch_blocking_timeout(request: &Request, timeout: Duration) -> crate::Result<Response> {
+    let timeout = request.timeout.map_or(timeout, |t| t.min(timeout));
+    fetch_blocking(&Request {
+        timeout: Some(timeout),
+        ..request.clone()
+    })
+}
+
+/// Download the body of the response to a file, resuming a previous partial download.
+///
+/// If the file already exists, a `Range` request is made for the rest of the body,
+/// which is then appended to the file. If the server doesn't support ranges
+/// (responds with `200` instead of `206`), the file is downloaded again from the start.
+///
+/// When the server tells us the full size, the final file size is verified.
+/// With the `checksum` feature, `Request::expected_sha256` is verified against the whole file.
+///
+/// Fails if the response isn't a 2xx.
+///
+/// ```
+/// # use std::io::{Read as _, Write as _};
+/// // A server for `hello world` that supports ranges on `/ranges`, but not on `/no-ranges`:
+/// let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+/// let url = format!("http://{}", listener.local_addr().unwrap());
+/// std::thread::spawn(move || {
+///     let body = b"hello world";
+///     for stream in listener.incoming() {
+///         let mut stream = stream.unwrap();
+///         let mut request = vec![0; 1024];
+///         let n = stream.read(&mut request).unwrap();
+///         let request = String::from_utf8_lossy(&request[..n]).to_lowercase();
+///         let start = request
+///             .lines()
+///             .find_map(|line| line.strip_prefix("range: bytes="))
+///             .and_then(|range| range.trim_end_matches('-').parse::<usize>().ok())
+///             .filter(|_| request.starts_with("get /ranges "));
+///         let _ = match start {
+///             Some(start) if start >= body.len() => write!(
+///                 stream,
+///                 "HTTP/1.1 416 Range Not Satisfiable\r\nContent-Range: bytes */{}\r\nContent-Length: 0\r\n\r\n",
+///                 body.len()
+///             ),
+///             Some(start) => write!(
+///                 stream,
+///                 "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes {start}-{}/{}\r\nContent-Length: {}\r\n\r\n",
+///                 body.len() - 1,
+///                 body.len(),
+///                 body.len() - start
+///             )
+///             .and_then(|()| stream.write_all(&body[start..])),
+///             None => write!(stream, "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n", body.len())
+///                 .and_then(|()| stream.write_all(body)),
+///         };
+///     }
+/// });
+/// let path = std::env::temp_dir().join(format!("ehttp-download-resumable-{}", std::process::id()));
+///
+/// // The server sends the rest of a partial download:
+/// std::fs::write(&path, "hello").unwrap();
+/// let response = ehttp::download_resumable(&ehttp::Request::get(format!("{url}/ranges")), &path)?;
+/// assert_eq!(response.status, 206);
+/// assert_eq!(std::fs::read(&path).unwrap(), b"hello world");
+///
+/// // The server sends the whole body, which replaces what we had:
+/// std::fs::write(&path, "HELLO").unwrap();
+/// let response = ehttp::download_resumable(&ehttp::Request::get(format!("{url}/no-ranges")), &path)?;
+/// assert_eq!(response.status, 200);
+/// assert_eq!(std::fs::read(&path).unwrap(), b"hello world");
+///
+/// // The download was already complete:
+/// let response = ehttp::download_resumable(&ehttp::Request::get(format!("{url}/ranges")), &path)?;
+/// assert_eq!(response.status, 416);
+/// assert_eq!(std::fs::read(&path).unwrap(), b"hello world");
+/// # std::fs::remove_file(&path).unwrap();
+/// # Ok::<(), ehttp::Error>(())
+/// ```
+///
+/// Only available when compiling for native.
+pub fn download_resumable(
+    request: &Request,
+    path: impl AsRef<Path>,
+) -> crate::Result<PartialResponse> {
+    let path = path.as_ref();
+    let offset = std::fs::metadata(path).map_or(0, |metadata| metadata.len());
+
+    let request = if offset > 0 {
+        request.clone().range(offset, None)
+    } else {
+        request.clone()
+    };
+    let (response, mut reader) = send(&request)?;
+
+    let range = response
+        .headers
+        .get("content-range")
+        .and_then(ContentRange::parse);
+
+    if response.status == 416 && offset > 0 {
+        // Range Not Satisfiable - maybe because we already have the whole file?
+        let complete_length = response
+            .headers
+            .get("content-range")
+            .and_then(|value| value.trim().strip_prefix("bytes */"))
+            .and_then(|length| length.parse::<u64>().ok());
+        if complete_length == Some(offset) {
+            verify_file(&request, path)?;
+            return Ok(response);
         }
     }
-    headers.sort(); // It reads nicer, and matches web backend.
+    if !response.ok {
+        return Err(format!("{} {}", response.status, response.status_text));
+    }
 
-    let mut reader = resp.into_reader();
-    let mut bytes = vec![];
-    use std::io::Read as _;
-    if let Err(err) = reader.read_to_end(&mut bytes) {
-        if request.method == "HEAD" && err.kind() == std::io::ErrorKind::UnexpectedEof {
-            // We don't really expect a body for HEAD requests, so this is fine.
+    let (mut file, expected_len) = if response.status == 206 && offset > 0 {
+        let range = range.ok_or("Partial response without a valid content-range header")?;
+        if range.start != offset {
+            return Err(format!(
+                "Asked to resume from byte {offset}, but the server resumed from byte {}",
+                range.start
+            ));
+        }
+        let file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(path)
+            .map_err(|err| format!("Failed to open {path:?}: {err}"))?;
+        (file, range.complete_length)
+    } else {
+        // The server ignored our range, so start over.
+        let file = std::fs::File::create(path)
+            .map_err(|err| format!("Failed to create {path:?}: {err}"))?;
+        let expected_len = if response.headers.get("content-encoding").is_none() {
+            response
+                .headers
+                .get("content-length")
+                .and_then(|len| len.parse().ok())
+        } else {
+            None // The length is of the compressed body
+        };
+        (file, expected_len)
+    };
+
+    std::io::copy(&mut reader, &mut file)
+        .map_err(|err| format!("Failed to download to {path:?}: {err}"))?;
+
+    let len = file
+        .metadata()
+        .map_err(|err| format!("Failed to read {path:?}: {err}"))?
+        .len();
+    if let Some(expected_len) = expected_len {
+        if len != expected_len {
+            return Err(format!(
+                "Download incomplete: got {len} of {expected_len} bytes"
+            ));
+        }
+    }
+
+    verify_file(&request, path)?;
+
+    Ok(response)
+}
+
+fn verify_file(request: &Request, path: &Path) -> crate::Result<()> {
+    #[cfg(feature = "checksum")]
+    if let Some(expected) = &request.expected_sha256 {
+        let mut file =
+            std::fs::File::open(path).map_err(|err| format!("Failed to open {path:?}: {err}"))?;
+        let mut verifier = crate::checksum::Sha256Verifier::new(expected);
+        let mut buf = vec![0; 64 * 1024];
+        loop {
+            match file.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => verifier.update(&buf[..n]),
+                Err(err) => return Err(format!("Failed to read {path:?}: {err}")),
+            }
+        }
+        verifier.finish()?;
+    }
+
+    #[cfg(not(feature = "checksum"))]
+    let _ = (request, path);
+
+    Ok(())
+}
+
+/// Send the request and read the status and headers of the response,
+/// following redirects according to [`Request::redirect_policy`].
+///
+/// The body is left for the caller to read, from the returned reader.
+/// A gzip-compressed body is decompressed by the reader,
+/// and its `content-encoding` and `content-length` headers are removed.
+pub(crate) fn send(request: &Request) -> crate::Result<(PartialResponse, ResponseReader)> {
+    let (response, reader, _) = send_with_trailers(request)?;
+    Ok((response, reader))
+}
+
+/// The trailers of a response, filled in once its body has been read to the end.
+///
+/// Only ever non-empty with the `own-client` feature.
+pub(crate) type Trailers = Arc<Mutex<BTreeMap<String, String>>>;
+
+/// Like [`send`], but also returns the [`Trailers`].
+pub(crate) fn send_with_trailers(
+    request: &Request,
+) -> crate::Result<(PartialResponse, ResponseReader, Trailers)> {
+    if let Some(path) = request.url.strip_prefix("file://") {
+        let (response, reader) = send_file(request, path)?;
+        return Ok((response, reader, Trailers::default()));
+    }
+    if let Some(data) = request.url.strip_prefix("data:") {
+        let (response, reader) = send_data(request, data)?;
+        return Ok((response, reader, Trailers::default()));
+    }
+
+    let Some(permit) = crate::limit::acquire()? else {
+        let (response, reader, trailers) = send_network(request)?;
+        return Ok((response, crate::handle::abortable(reader), trailers));
+    };
+    let (response, reader, trailers) = send_network(request)?;
+    let reader = crate::handle::abortable(reader);
+    let reader = Box::new(PermitReader {
+        reader,
+        _permit: permit,
+    });
+    Ok((response, reader, trailers))
+}
+
+/// [`send`] for `http://` and `https://` urls.
+fn send_network(request: &Request) -> crate::Result<(PartialResponse, ResponseReader, Trailers)> {
+    request.check_https_only()?;
+    let mut current = if split_userinfo(&request.url).is_some() {
+        Cow::Owned(request.clone().basic_auth_from_url())
+    } else {
+        Cow::Borrowed(request)
+    };
+    if let Some(fixed) = current.check_content_length()? {
+        current = Cow::Owned(fixed);
+    }
+    #[cfg(feature = "own-client")]
+    if request.asterisk_form && !request.method.eq_ignore_ascii_case("OPTIONS") {
+        return Err(format!(
+            "Only OPTIONS requests can be sent to `*`, not {}",
+            request.method
+        ));
+    }
+    match request.version {
+        None | Some(HttpVersion::Http11) => {}
+        #[cfg(feature = "own-client")]
+        Some(HttpVersion::Http10) => {}
+        #[cfg(not(feature = "own-client"))]
+        Some(HttpVersion::Http10) => {
+            return Err(
+                "Sending HTTP/1.0 requests needs the `own-client` feature of ehttp".to_owned(),
+            );
+        }
+        Some(version) => return Err(format!("Sending {version} requests is not supported")),
+    }
+    let mut num_redirects = 0;
+    let mut num_retries = 0;
+
+    let (mut response, reader, trailers) = loop {
+        let (response, reader, trailers) = send_once(&current)?;
+        if let Some(next) = redirected_request(&current, &response, num_redirects)? {
+            next.check_https_only()?;
+            current = Cow::Owned(next);
+            num_redirects += 1;
+            continue;
+        }
+        let retry_delay = if current.body_reader.is_none() {
+            let retry_after = response.headers.get("retry-after");
+            current
+                .status_retry
+                .delay_after(num_retries, response.status, retry_after)
         } else {
-            return Err(format!("Failed to read response body: {err}"));
+            None // We can't send the body again
+        };
+        match retry_delay {
+            Some(delay) => {
+                drop(reader);
+                std::thread::sleep(delay);
+                num_retries += 1;
+            }
+            None => break (response, reader, trailers),
+        }
+    };
+
+    response.requested_url = without_userinfo(&request.url);
+    let (response, reader) = decode_response(request, response, reader);
+    Ok((response, reader, trailers))
+}
+
+/// Wrap the body reader to decompress it and report progress, as [`send`] describes.
+fn decode_response(
+    request: &Request,
+    mut response: PartialResponse,
+    reader: ResponseReader,
+) -> (PartialResponse, ResponseReader) {
+    let headers = &mut response.headers;
+    headers.sort(); // It reads nicer, and matches web backend.
+
+    let is_gzip = headers.get("content-encoding").map_or(false, |encoding| {
+        encoding.trim().eq_ignore_ascii_case("gzip")
+            || encoding.trim().eq_ignore_ascii_case("x-gzip")
+    });
+    let reader: ResponseReader = if is_gzip {
+        response.decoded_from = headers
+            .get("content-encoding")
+            .map(|encoding| encoding.trim().to_owned());
+        headers.remove("content-encoding");
+        headers.remove("content-length"); // The length is of the compressed body
+        Box::new(GzipReader::new(reader, request.decompression_limits))
+    } else {
+        reader
+    };
+    let reader: ResponseReader = match &request.progress.download {
+        Some(on_download) => {
+            let total = headers
+                .get("content-length")
+                .and_then(|len| len.parse().ok());
+            Box::new(ProgressReader::new(reader, total, on_download))
+        }
+        None => reader,
+    };
+
+    (response, reader)
+}
+
+/// Connect to the host of the url for our own minimal client,
+/// with the timeout and options of the request.
+///
+/// Like `ureq`, each address the host resolves to is tried in turn, until one connects,
+/// e.g. `127.0.0.1` after `::1` for `localhost`:
+///
+/// ```
+/// # use std::io::{Read as _, Write as _};
+/// # let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+/// # let port = listener.local_addr().unwrap().port();
+/// # std::thread::spawn(move || {
+/// #     let (mut stream, _) = listener.accept().unwrap();
+/// #     let _ = stream.read(&mut [0; 1024]).unwrap();
+/// #     stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nhi").unwrap();
+/// # });
+/// // Nothing listens on the first address:
+/// let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+/// ehttp::set_dns_resolver(Some(std::sync::Arc::new(move |_host: &str| {
+///     Ok(vec![closed, std::net::SocketAddr::from(([127, 0, 0, 1], port))])
+/// })));
+/// let response = ehttp::fetch_blocking(&ehttp::Request::get(format!("http://server.test:{port}/")))?;
+/// assert_eq!(response.text(), Some("hi"));
+/// # Ok::<(), ehttp::Error>(())
+/// ```
+pub(crate) fn connect_tcp(request: &Request, url: &url::Url) -> crate::Result<std::net::TcpStream> {
+    let host = url_host(url)?;
+    let port = url.port_or_known_default().unwrap_or(80);
+
+    let timeout = request.timeout.or_else(crate::default_timeout);
+    let deadline = timeout.map(|timeout| std::time::Instant::now() + timeout);
+    let addrs = crate::dns::resolve(&format!("{}:{port}", url.host_str().unwrap_or(host)))
+        .map_err(|err| format!("Failed to look up {host}: {err}"))?;
+
+    let mut error = format!("Failed to look up {host}");
+    let mut connected = None;
+    for addr in addrs {
+        // The timeout is for connecting at all, not for each address:
+        let result = match deadline {
+            Some(deadline) => {
+                let remaining = deadline.saturating_duration_since(std::time::Instant::now());
+                if remaining.is_zero() {
+                    error = format!("{CONNECT_TIMEOUT}Failed to connect to {host}: timed out");
+                    break;
+                }
+                std::net::TcpStream::connect_timeout(&addr, remaining)
+            }
+            None => std::net::TcpStream::connect(addr),
+        };
+        match result {
+            Ok(tcp) => {
+                connected = Some(tcp);
+                break;
+            }
+            Err(err) => {
+                let message = format!("Failed to connect to {addr}: {err}");
+                error = timeout_error(CONNECT_TIMEOUT, &err, message);
+            }
+        }
+    }
+    let tcp = connected.ok_or(error)?;
+    let io_err = |err: std::io::Error| err.to_string();
+    tcp.set_read_timeout(timeout).map_err(io_err)?;
+    tcp.set_write_timeout(timeout).map_err(io_err)?;
+    tcp.set_nodelay(request.tcp_nodelay).map_err(io_err)?;
+    crate::handle::register_socket(&tcp)?;
+    Ok(tcp)
+}
+
+/// The host of the url, without the brackets of an IPv6 address.
+pub(crate) fn url_host(url: &url::Url) -> crate::Result<&str> {
+    let host = url.host_str().ok_or("The url has no host")?;
+    Ok(host.trim_start_matches('[').trim_end_matches(']'))
+}
+
+/// Read the status line and headers of the response to `request`, for our own minimal client.
+///
+/// Interim `1xx` responses (e.g. `100 Continue` or `103 Early Hints`) are skipped,
+/// except for `101 Switching Protocols`, which is the last response on the connection.
+pub(crate) fn read_response_head(
+    reader: &mut impl BufRead,
+    request: &Request,
+) -> crate::Result<PartialResponse> {
+    loop {
+        let response = read_one_response_head(reader, request)?;
+        if !(100..200).contains(&response.status) || response.status == 101 {
+            return Ok(response);
+        }
+    }
+}
+
+/// The longest header line of a response that we accept, whatever the [`Request::max_response_headers_size`].
+///
+/// The same as in `ureq`.
+const MAX_HEADER_LINE: usize = 100 * 1024;
+
+/// The most headers a response may have, like in `ureq`.
+const MAX_HEADERS: usize = 100;
+
+fn read_one_response_head(
+    reader: &mut impl BufRead,
+    request: &Request,
+) -> crate::Result<PartialResponse> {
+    let mut lines = vec![];
+    let mut headers_size = 0;
+    loop {
+        let mut line = vec![];
+        let limit =
+            (request.max_response_headers_size - headers_size).min(MAX_HEADER_LINE) as u64 + 1;
+        (&mut *reader)
+            .take(limit)
+            .read_until(b'\n', &mut line)
+            .map_err(|err| {
+                timeout_error(
+                    READ_TIMEOUT,
+                    &err,
+                    format!("Failed to read response: {err}"),
+                )
+            })?;
+        headers_size += line.len();
+        if request.max_response_headers_size < headers_size {
+            return Err(format!(
+                "The response headers are more than the limit of {} bytes",
+                request.max_response_headers_size
+            ));
+        }
+        if MAX_HEADER_LINE < line.len() {
+            return Err(format!(
+                "A response header line is more than the limit of {MAX_HEADER_LINE} bytes"
+            ));
+        }
+        if !line.ends_with(b"\n") {
+            return Err(
+                "The connection was closed before the end of the response headers".to_owned(),
+            );
+        }
+        let line = String::from_utf8_lossy(&line).trim_end().to_owned();
+        if line.is_empty() {
+            break;
+        }
+        if MAX_HEADERS < lines.len() {
+            // The status line and this many headers are already in `lines`.
+            return Err(format!(
+                "The response has more than the limit of {MAX_HEADERS} headers"
+            ));
+        }
+        lines.push(line);
+    }
+
+    let mut lines = lines.into_iter();
+    let status_line = lines.next().unwrap_or_default();
+    let mut parts = status_line.splitn(3, ' ');
+    let version = parts.next().unwrap_or_default().parse().ok();
+    let status: u16 = parts
+        .next()
+        .and_then(|status| status.parse().ok())
+        .ok_or_else(|| format!("Bad status line: {status_line:?}"))?;
+    let status_text = parts.next().unwrap_or_default().to_owned();
+
+    let mut headers = crate::Headers::default();
+    for line in lines {
+        if let Some((key, value)) = line.split_once(':') {
+            headers.insert(key.trim().to_lowercase(), value.trim());
         }
     }
 
-    let response = Response {
-        url,
-        ok,
+    Ok(PartialResponse {
+        url: request.url.clone(),
+        requested_url: request.url.clone(),
+        ok: (200..300).contains(&status),
         status,
         status_text,
         headers,
-        bytes,
+        version,
+        decoded_from: None,
+    })
+}
+
+/// Respond with the contents of a local file, for `file://` urls.
+///
+/// A missing file results in a `404`.
+fn send_file(request: &Request, path: &str) -> crate::Result<(PartialResponse, ResponseReader)> {
+    if request.method != "GET" && request.method != "HEAD" {
+        return Err(format!(
+            "Only GET and HEAD are supported for file:// urls, not {}",
+            request.method
+        ));
+    }
+
+    let path = path.strip_prefix("localhost").unwrap_or(path);
+    let path = path
+        .split(|c| c == '?' || c == '#')
+        .next()
+        .unwrap_or_default();
+    let path = percent_decode(path);
+    // `file:///C:/dir/file` on Windows:
+    let path = match path.strip_prefix('/') {
+        Some(windows_path) if windows_path.get(1..2) == Some(":") => windows_path,
+        _ => path.as_str(),
     };
-    Ok(response)
+
+    let response = |status: u16, headers| PartialResponse {
+        url: request.url.clone(),
+        requested_url: request.url.clone(),
+        ok: (200..300).contains(&status),
+        status,
+        status_text: if status == 200 { "OK" } else { "Not Found" }.to_owned(),
+        headers,
+        version: None,
+        decoded_from: None,
+    };
+
+    let file = match std::fs::File::open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            return Ok((
+                response(404, Default::default()),
+                Box::new(std::io::empty()),
+            ));
+        }
+        Err(err) => return Err(format!("Failed to open {path:?}: {err}")),
+    };
+    let len = file
+        .metadata()
+        .map_err(|err| format!("Failed to read {path:?}: {err}"))?
+        .len();
+
+    let mut headers = crate::Headers::default();
+    headers.insert("content-length", len);
+    #[cfg(feature = "multipart")]
+    let content_type = mime_guess::from_path(path).first_or_octet_stream();
+    #[cfg(not(feature = "multipart"))]
+    let content_type = "application/octet-stream";
+    headers.insert("content-type", content_type);
+
+    let reader: ResponseReader = if request.method == "HEAD" {
+        Box::new(std::io::empty())
+    } else {
+        Box::new(file)
+    };
+    Ok((response(200, headers), reader))
+}
+
+/// Respond with the data of a `data:` url, as defined by RFC 2397.
+fn send_data(request: &Request, data: &str) -> crate::Result<(PartialResponse, ResponseReader)> {
+    let (media_type, data) = data
+        .split_once(',')
+        .ok_or("Invalid data: url, it has no ','")?;
+    let (media_type, is_base64) = match media_type.strip_suffix(";base64") {
+        Some(media_type) => (media_type, true),
+        None => (media_type, false),
+    };
+    let content_type = match media_type {
+        "" => "text/plain;charset=US-ASCII".to_owned(),
+        media_type if media_type.starts_with(';') => format!("text/plain{media_type}"),
+        media_type => percent_decode(media_type),
+    };
+
+    let bytes = percent_decode_bytes(data);
+    let bytes = if is_base64 {
+        use base64::Engine as _;
+        let engine = base64::engine::GeneralPurpose::new(
+            &base64::alphabet::STANDARD,
+            base64::engine::GeneralPurposeConfig::new()
+                .with_decode_padding_mode(base64::engine::DecodePaddingMode::Indifferent),
+        );
+        let encoded: Vec<u8> = bytes
+            .into_iter()
+            .filter(|b| !b.is_ascii_whitespace())
+            .collect();
+        engine
+            .decode(encoded)
+            .map_err(|err| format!("Invalid base64 in data: url: {err}"))?
+    } else {
+        bytes
+    };
+
+    let mut headers = crate::Headers::default();
+    headers.insert("content-length", bytes.len());
+    headers.insert("content-type", content_type);
+
+    let response = PartialResponse {
+        url: request.url.clone(),
+        requested_url: request.url.clone(),
+        ok: true,
+        status: 200,
+        status_text: "OK".to_owned(),
+        headers,
+        version: None,
+        decoded_from: None,
+    };
+    let bytes = if request.method == "HEAD" {
+        vec![]
+    } else {
+        bytes
+    };
+    Ok((response, Box::new(std::io::Cursor::new(bytes))))
+}
+
+pub(crate) type ResponseReader = Box<dyn Read + Send + Sync + 'static>;
+
+/// Only check the compression ratio after this many decompressed bytes.
+const MIN_SIZE_FOR_RATIO_CHECK: u64 = 1024 * 1024;
+
+/// Decompresses a gzip body, and fails if it exceeds the [`DecompressionLimits`].
+struct GzipReader {
+    decoder: flate2::read::MultiGzDecoder<CountingReader>,
+    decompressed: u64,
+    limits: DecompressionLimits,
+}
+
+impl GzipReader {
+    fn new(reader: ResponseReader, limits: DecompressionLimits) -> Self {
+        Self {
+            decoder: flate2::read::MultiGzDecoder::new(CountingReader {
+                reader,
+                num_bytes: 0,
+            }),
+            decompressed: 0,
+            limits,
+        }
+    }
+}
+
+impl Read for GzipReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = match self.decoder.read(buf) {
+            // An empty body, e.g. of a `HEAD` or `304`, isn't valid gzip, but is fine.
+            Err(err)
+                if err.kind() == std::io::ErrorKind::UnexpectedEof
+                    && self.decoder.get_ref().num_bytes == 0 =>
+            {
+                0
+            }
+            result => result?,
+        };
+        self.decompressed += n as u64;
+
+        let DecompressionLimits {
+            max_size,
+            max_ratio,
+        } = self.limits;
+        if let Some(max_size) = max_size {
+            if self.decompressed > max_size {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::Other,
+                    format!("The decompressed body is larger than the limit of {max_size} bytes"),
+                ));
+            }
+        }
+        if let Some(max_ratio) = max_ratio {
+            let compressed = self.decoder.get_ref().num_bytes;
+            if self.decompressed > MIN_SIZE_FOR_RATIO_CHECK
+                && self.decompressed > compressed.saturating_mul(max_ratio)
+            {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::Other,
+                    format!(
+                        "The body decompressed to more than {max_ratio} times its compressed size"
+                    ),
+                ));
+            }
+        }
+
+        Ok(n)
+    }
+}
+
+/// Reports the number of bytes read through it to a [`ProgressCallback`].
+pub(crate) struct ProgressReader<R> {
+    reader: R,
+    num_bytes: u64,
+    total: Option<u64>,
+    callback: ProgressCallback,
+}
+
+impl<R> ProgressReader<R> {
+    pub(crate) fn new(reader: R, total: Option<u64>, callback: &ProgressCallback) -> Self {
+        Self {
+            reader,
+            num_bytes: 0,
+            total,
+            callback: callback.clone(),
+        }
+    }
+}
+
+impl<R: Read> Read for ProgressReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.reader.read(buf)?;
+        if n > 0 {
+            self.num_bytes += n as u64;
+            (self.callback)(self.num_bytes, self.total);
+        }
+        Ok(n)
+    }
+}
+
+/// Keeps the request counted as in flight for the [`crate::ConcurrencyLimit`] until the body is read.
+struct PermitReader {
+    reader: ResponseReader,
+    _permit: crate::limit::Permit,
+}
+
+impl Read for PermitReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.reader.read(buf)
+    }
+}
+
+/// Counts the bytes read through it.
+struct CountingReader {
+    reader: ResponseReader,
+    num_bytes: u64,
+}
+
+impl Read for CountingReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.reader.read(buf)?;
+        self.num_bytes += n as u64;
+        Ok(n)
+    }
+}
+
+/// Send the request once with our own client, without following redirects or retrying.
+#[cfg(feature = "own-client")]
+fn send_once(request: &Request) -> crate::Result<(PartialResponse, ResponseReader, Trailers)> {
+    crate::own_client::send(request)
+}
+
+/// Send the request once with `ureq`, without following redirects or retrying.
+#[cfg(not(feature = "own-client"))]
+fn send_once(request: &Request) -> crate::Result<(PartialResponse, ResponseReader, Trailers)> {
+    let resp = send_ureq(request)?;
+    let mut headers = crate::Headers::default();
+    for key in header_names(&resp) {
+        for value in resp.all(&key) {
+            headers.insert(&key, value);
+        }
+    }
+    let response = PartialResponse {
+        url: resp.get_url().to_owned(),
+        requested_url: request.url.clone(),
+        ok: (200..300).contains(&resp.status()),
+        status: resp.status(),
+        status_text: resp.status_text().to_owned(),
+        headers,
+        version: resp.http_version().parse().ok(),
+        decoded_from: None,
+    };
+    Ok((response, resp.into_reader(), Trailers::default()))
+}
+
+#[cfg(not(feature = "own-client"))]
+fn send_ureq(request: &Request) -> crate::Result<ureq::Response> {
+    let request = &*request.signed();
+    let req = create_request(request)?;
+
+    let resp = match (&request.body_reader, &request.progress.upload) {
+        (Some(body_reader), None) => req.send(body_reader.take()?),
+        (Some(body_reader), Some(on_upload)) => {
+            let total = request
+                .headers
+                .get("content-length")
+                .and_then(|len| len.parse().ok());
+            req.send(ProgressReader::new(body_reader.take()?, total, on_upload))
+        }
+        (None, _) if request.body.is_empty() => {
+            let needs_length = request.empty_content_length
+                && matches!(&*request.method, "POST" | "PUT" | "PATCH")
+                && request.headers.get("content-length").is_none();
+            if needs_length {
+                req.set("Content-Length", "0").call()
+            } else {
+                req.call()
+            }
+        }
+        (None, None) => req.send_bytes(&request.body),
+        (None, Some(on_upload)) => {
+            let total = request.body.len() as u64;
+            let req = if request.headers.get("content-length").is_none() {
+                // Like `send_bytes` would, to avoid a chunked upload.
+                req.set("Content-Length", &total.to_string())
+            } else {
+                req
+            };
+            req.send(ProgressReader::new(
+                request.body.as_slice(),
+                Some(total),
+                on_upload,
+            ))
+        }
+    };
+
+    let resp = match resp {
+        Ok(resp) => resp,
+        Err(ureq::Error::Status(_, resp)) => resp, // Still read the body on e.g. 404
+        Err(ureq::Error::Transport(err)) => return Err(transport_error(&err)),
+    };
+
+    let headers_size = headers_size(&resp);
+    if request.max_response_headers_size < headers_size {
+        return Err(format!(
+            "The response headers are {headers_size} bytes, more than the limit of {} bytes",
+            request.max_response_headers_size
+        ));
+    }
+
+    Ok(resp)
+}
+
+/// The size of the status line and headers, as received.
+#[cfg(not(feature = "own-client"))]
+fn headers_size(resp: &ureq::Response) -> usize {
+    let status_line = resp.http_version().len() + 5 + resp.status_text().len() + 2; // "HTTP/1.1 200 OK\r\n"
+    let mut size = status_line + 2; // The final empty line
+    for key in header_names(resp) {
+        for value in resp.all(&key) {
+            size += key.len() + 2 + value.len() + 2; // "key: value\r\n"
+        }
+    }
+    size
+}
+
+/// The lowercase header names of the response, each only once even if it has several values.
+#[cfg(not(feature = "own-client"))]
+fn header_names(resp: &ureq::Response) -> Vec<String> {
+    let mut names = resp.headers_names();
+    let mut seen = std::collections::HashSet::new();
+    names.retain(|name| seen.insert(name.clone()));
+    names
+}
+
+/// If the response is a redirect that the [`crate::RedirectPolicy`] says we should follow,
+/// returns the request to follow it with.
+fn redirected_request(
+    request: &Request,
+    response: &PartialResponse,
+    num_redirects: u32,
+) -> crate::Result<Option<Request>> {
+    let policy = &request.redirect_policy;
+    let status = response.status;
+
+    if !matches!(status, 301 | 302 | 303 | 307 | 308) || policy.max_redirects <= num_redirects {
+        return Ok(None);
+    }
+    let Some(location) = response.headers.get("location") else {
+        return Ok(None);
+    };
+
+    let from = url::Url::parse(&response.url).map_err(|err| format!("Bad url: {err}"))?;
+    let to = from
+        .join(location)
+        .map_err(|err| format!("Bad redirect location {location:?}: {err}"))?;
+
+    let mut next = request.clone();
+    next.url = to.to_string();
+
+    let change_to_get = match status {
+        301 | 302 => policy.post_to_get && request.method == "POST",
+        303 => request.method != "GET" && request.method != "HEAD",
+        _ => false,
+    };
+    if change_to_get {
+        next.method = Cow::Borrowed("GET");
+        next.body = vec![];
+        next.body_reader = None;
+        next.headers.remove("content-type");
+        next.headers.remove("content-length");
+    } else if next.body_reader.is_some() {
+        // We can't send the body again, so let the caller handle the redirect.
+        return Ok(None);
+    }
+
+    if from.origin() != to.origin() && !policy.forward_credentials {
+        for key in ["authorization", "cookie", "proxy-authorization"] {
+            next.headers.remove(key);
+        }
+    }
+    if from.origin() != to.origin() {
+        next.headers.remove("host"); // An override of `Request::host` is for the original server
+        #[cfg(feature = "own-client")]
+        {
+            next.sni = None; // And so is an override of `Request::sni`
+        }
+    }
+
+    Ok(Some(next))
+}
+
+/// Create the `ureq` request, with the options and headers of the given [`Request`].
+#[cfg(not(feature = "own-client"))]
+pub(crate) fn create_request(request: &Request) -> crate::Result<ureq::Request> {
+    // A fresh agent per request means no connection pool is shared between requests.
+    // `ureq` also never pipelines: it waits for each response before reusing a connection.
+    let mut agent = ureq::AgentBuilder::new()
+        .redirects(0) // We follow redirects ourselves, in `send`
+        .no_delay(request.tcp_nodelay)
+        .resolver(crate::dns::resolve);
+
+    if let Some(proxy) = &request.proxy {
+        agent = agent.proxy(ureq_proxy(proxy)?);
+    }
+
+    let agent = agent.build();
+
+    let mut req = agent.request(&request.method, &request.url);
+
+    if let Some(proxy) = &request.proxy {
+        // `ureq` only authenticates the `CONNECT` of `https://` requests,
+        // so we need to authenticate plain `http://` requests ourselves.
+        if let Some((user, password)) = proxy_credentials(proxy) {
+            if request.url.starts_with("http://") {
+                use base64::Engine as _;
+                let credentials =
+                    base64::engine::general_purpose::STANDARD.encode(format!("{user}:{password}"));
+                req = req.set("Proxy-Authorization", &format!("Basic {credentials}"));
+            }
+        }
+    }
+
+    if let Some(timeout) = request.timeout.or_else(crate::default_timeout) {
+        req = req.timeout(timeout);
+    }
+
+    for (k, v) in combined_headers(&request.headers) {
+        req = req.set(k, &v);
+    }
+
+    // Like `ureq` would, but we decompress ourselves, to enforce the `DecompressionLimits`.
+    if request.headers.get("accept-encoding").is_none() && request.headers.get("range").is_none() {
+        req = req.set("accept-encoding", "gzip");
+    }
+
+    Ok(req)
+}
+
+/// Combine the values of repeated header keys, since `ureq` only keeps the last one
+/// (except for keys starting with `x-`).
+pub(crate) fn combined_headers(headers: &crate::Headers) -> Vec<(&str, Cow<'_, str>)> {
+    let mut combined: Vec<(&str, Cow<'_, str>)> = vec![];
+    for (key, value) in headers {
+        let is_x_header = key
+            .get(..2)
+            .map_or(false, |prefix| prefix.eq_ignore_ascii_case("x-"));
+        let previous = combined
+            .iter_mut()
+            .find(|(previous_key, _)| previous_key.eq_ignore_ascii_case(key));
+        match previous {
+            Some((_, previous_value)) if !is_x_header => {
+                let separator = if key.eq_ignore_ascii_case("cookie") {
+                    "; "
+                } else {
+                    ", "
+                };
+                *previous_value = format!("{previous_value}{separator}{value}").into();
+            }
+            _ => combined.push((key, value.into())),
+        }
+    }
+    combined
+}
+
+#[cfg(not(feature = "own-client"))]
+fn ureq_proxy(proxy: &crate::Proxy) -> crate::Result<ureq::Proxy> {
+    let (protocol, rest) = proxy
+        .url
+        .split_once("://")
+        .unwrap_or(("http", proxy.url.as_str()));
+    let url = match proxy_credentials(proxy) {
+        Some((user, password)) => {
+            let host = rest.rsplit_once('@').map_or(rest, |(_, host)| host);
+            format!("{protocol}://{user}:{password}@{host}")
+        }
+        None => proxy.url.clone(),
+    };
+    ureq::Proxy::new(url).map_err(|err| format!("Bad proxy url: {err}"))
+}
+
+pub(crate) fn proxy_credentials(proxy: &crate::Proxy) -> Option<(String, String)> {
+    if let Some(credentials) = &proxy.credentials {
+        return Some(credentials.clone());
+    }
+    let rest = proxy
+        .url
+        .split_once("://")
+        .map_or(&*proxy.url, |(_, rest)| rest);
+    let (userinfo, _host) = rest.rsplit_once('@')?;
+    let (user, password) = userinfo.split_once(':')?;
+    Some((user.to_owned(), password.to_owned()))
 }
 
 // ----------------------------------------------------------------------------
@@ -0,0 +1,165 @@
+use std::net::IpAddr;
+
+/// Which hosts to connect to directly rather than through a [`crate::Proxy`], as in the `NO_PROXY` environment variable.
+///
+/// The list is separated by commas (and/or whitespace), and each entry is one of:
+/// * `*`, which matches every host
+/// * a domain, e.g. `example.com` or `.example.com`, which matches the domain and all its subdomains
+/// * an IP address, e.g. `10.1.2.3` or `::1`, which matches only that address
+/// * an IP range in CIDR notation, e.g. `10.0.0.0/8` or `fd00::/8`
+///
+/// Matching is case-insensitive. Any port in an entry is ignored, and so are entries that can't be parsed.
+/// Domains are never resolved: `localhost` doesn't match `127.0.0.1`, and `10.0.0.0/8` only matches
+/// urls with an IP address as the host.
+///
+/// ```
+/// let no_proxy = ehttp::NoProxy::parse("localhost, .example.com, internal.test:8080, 10.0.0.0/8, ::1");
+///
+/// // Exact matches:
+/// assert!(no_proxy.matches("http://localhost:3000/"));
+/// assert!(no_proxy.matches("http://internal.test/"));
+/// assert!(no_proxy.matches("http://[::1]/"));
+///
+/// // Domain suffixes:
+/// assert!(no_proxy.matches("https://example.com/"));
+/// assert!(no_proxy.matches("https://api.EXAMPLE.com/v1"));
+/// assert!(!no_proxy.matches("https://notexample.com/"));
+///
+/// // CIDR ranges:
+/// assert!(no_proxy.matches("http://10.20.30.40/"));
+/// assert!(!no_proxy.matches("http://11.0.0.1/"));
+/// assert!(!no_proxy.matches("http://127.0.0.1/"));
+///
+/// // The wildcard:
+/// assert!(ehttp::NoProxy::parse("*").matches("https://www.rust-lang.org/"));
+/// assert!(!ehttp::NoProxy::parse("").matches("https://www.rust-lang.org/"));
+/// ```
+///
+/// Only available when compiling for native.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct NoProxy {
+    entries: Vec<Entry>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Entry {
+    Everything,
+
+    /// Lowercase, without leading or trailing dots.
+    Domain(String),
+
+    /// An address and how many of the leading bits of it must match.
+    Ip(IpAddr, u8),
+}
+
+impl NoProxy {
+    /// Parse a comma-separated list of hosts, as in the `NO_PROXY` environment variable.
+    pub fn parse(list: &str) -> Self {
+        let entries = list
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter_map(parse_entry)
+            .collect();
+        Self { entries }
+    }
+
+    /// Read the `NO_PROXY` (or `no_proxy`) environment variable.
+    ///
+    /// An empty list if neither is set.
+    pub fn from_env() -> Self {
+        let list = std::env::var("NO_PROXY")
+            .or_else(|_| std::env::var("no_proxy"))
+            .unwrap_or_default();
+        Self::parse(&list)
+    }
+
+    /// Is the list empty, so no host bypasses the proxy?
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Should a request to this url bypass the proxy?
+    ///
+    /// `false` for urls that can't be parsed or have no host.
+    pub fn matches(&self, url: &str) -> bool {
+        let Ok(url) = url::Url::parse(url) else {
+            return false;
+        };
+        let host = match url.host() {
+            Some(url::Host::Domain(domain)) => Host::Domain(domain.trim_end_matches('.')),
+            Some(url::Host::Ipv4(ip)) => Host::Ip(ip.into()),
+            Some(url::Host::Ipv6(ip)) => Host::Ip(ip.into()),
+            None => return false,
+        };
+        self.entries.iter().any(|entry| match (entry, &host) {
+            (Entry::Everything, _) => true,
+            (Entry::Domain(domain), Host::Domain(host)) => {
+                // `url` has already lowercased the host.
+                host.strip_suffix(domain.as_str())
+                    .map_or(false, |rest| rest.is_empty() || rest.ends_with('.'))
+            }
+            (Entry::Ip(network, prefix_len), Host::Ip(ip)) => {
+                in_network(*ip, *network, *prefix_len)
+            }
+            _ => false,
+        })
+    }
+}
+
+enum Host<'a> {
+    Domain(&'a str),
+    Ip(IpAddr),
+}
+
+fn parse_entry(entry: &str) -> Option<Entry> {
+    let entry = entry.trim();
+    if entry.is_empty() {
+        return None;
+    }
+    if entry == "*" {
+        return Some(Entry::Everything);
+    }
+
+    if let Some((ip, prefix_len)) = entry.split_once('/') {
+        let ip: IpAddr = ip
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse()
+            .ok()?;
+        let prefix_len: u8 = prefix_len.parse().ok()?;
+        let max_len = if ip.is_ipv4() { 32 } else { 128 };
+        return (prefix_len <= max_len).then_some(Entry::Ip(ip, prefix_len));
+    }
+    if let Ok(ip) = entry.parse::<IpAddr>() {
+        return Some(Entry::Ip(ip, if ip.is_ipv4() { 32 } else { 128 }));
+    }
+    if let Some(rest) = entry.strip_prefix('[') {
+        // `[::1]` or `[::1]:8080`
+        let ip = rest.split_once(']')?.0.parse::<IpAddr>().ok()?;
+        return Some(Entry::Ip(ip, 128));
+    }
+
+    let host = entry.rsplit_once(':').map_or(entry, |(host, _port)| host);
+    if let Ok(ip) = host.parse::<IpAddr>() {
+        return Some(Entry::Ip(ip, 32));
+    }
+    let domain = host
+        .trim_start_matches('*')
+        .trim_matches('.')
+        .to_ascii_lowercase();
+    (!domain.is_empty()).then_some(Entry::Domain(domain))
+}
+
+/// Are the first `prefix_len` bits of `ip` the same as those of `network`?
+fn in_network(ip: IpAddr, network: IpAddr, prefix_len: u8) -> bool {
+    let (ip, network, bits) = match (ip, network) {
+        (IpAddr::V4(ip), IpAddr::V4(network)) => (
+            u128::from(u32::from(ip)),
+            u128::from(u32::from(network)),
+            32,
+        ),
+        (IpAddr::V6(ip), IpAddr::V6(network)) => (u128::from(ip), u128::from(network), 128),
+        _ => return false,
+    };
+    let shift = bits - u32::from(prefix_len);
+    shift >= bits || (ip >> shift) == (network >> shift)
+}
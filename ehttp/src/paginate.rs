@@ -0,0 +1,115 @@
+use crate::{Request, Response};
+
+/// Fetch all the pages of a paginated API, by following the `link` headers with `rel="next"`.
+///
+/// The iterator yields one response per page, starting with the response to `initial`.
+/// Each next page is requested like `initial` (same method, headers and body), but with the
+/// url from [`Response::next_page_url`]. It ends after a page without a next link,
+/// e.g. a single page without any `link` header, or after the first `Err`.
+///
+/// The `Authorization`, `Cookie` and `Proxy-Authorization` headers are only sent to the origin
+/// of `initial`, unless [`crate::RedirectPolicy::forward_credentials`] is set.
+///
+/// ```no_run
+/// for page in ehttp::paginate(ehttp::Request::get("https://api.github.com/repos/emilk/ehttp/issues")) {
+///     let page = page?;
+///     println!("{}", page.text().unwrap_or_default());
+/// }
+/// # Ok::<(), ehttp::Error>(())
+/// ```
+///
+/// Only available when compiling for native.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn paginate(initial: Request) -> impl Iterator<Item = crate::Result<Response>> {
+    let forward_credentials = initial.redirect_policy.forward_credentials;
+    let mut pages = Pages::new(initial, forward_credentials);
+    std::iter::from_fn(move || {
+        let request = pages.next.take()?;
+        let result = crate::fetch_blocking(&request);
+        if let Ok(response) = &result {
+            pages.prepare_next(&request, response);
+        }
+        Some(result)
+    })
+}
+
+#[cfg_attr(
+    not(target_arch = "wasm32"),
+    doc = "Like [`paginate()`], but as an `async` [`Stream`](futures_util::Stream)."
+)]
+#[cfg_attr(
+    target_arch = "wasm32",
+    doc = "Fetch all the pages of a paginated API as an `async` [`Stream`](futures_util::Stream), \
+           by following the `link` headers with `rel=\"next\"`."
+)]
+///
+/// On web the credentials are always forwarded, as it is up to the browser which cookies to send.
+///
+/// Available on web, and on native behind the `native-async` feature.
+/// Requires the `streaming` feature to be enabled.
+#[cfg(all(
+    feature = "streaming",
+    any(target_arch = "wasm32", feature = "native-async")
+))]
+pub fn paginate_async(
+    initial: Request,
+) -> impl futures_util::Stream<Item = crate::Result<Response>> {
+    #[cfg(not(target_arch = "wasm32"))]
+    let forward_credentials = initial.redirect_policy.forward_credentials;
+    #[cfg(target_arch = "wasm32")]
+    let forward_credentials = true;
+
+    let pages = Pages::new(initial, forward_credentials);
+    futures_util::stream::unfold(pages, |mut pages| async move {
+        let request = pages.next.take()?;
+        let result = crate::fetch_async(request.clone()).await;
+        if let Ok(response) = &result {
+            pages.prepare_next(&request, response);
+        }
+        Some((result, pages))
+    })
+}
+
+struct Pages {
+    next: Option<Request>,
+    origin: Option<String>,
+    forward_credentials: bool,
+}
+
+impl Pages {
+    fn new(initial: Request, forward_credentials: bool) -> Self {
+        Self {
+            origin: origin(&initial.url),
+            next: Some(initial),
+            forward_credentials,
+        }
+    }
+
+    fn prepare_next(&mut self, request: &Request, response: &Response) {
+        let Some(url) = response.next_page_url() else {
+            return;
+        };
+        if url == response.url || url == request.url {
+            return; // A next link to itself would never end
+        }
+        let mut next = request.clone().with_url(url);
+        if !self.forward_credentials && origin(&next.url) != self.origin {
+            for key in Request::SENSITIVE_HEADERS {
+                next.headers.remove(key);
+            }
+        }
+        self.next = Some(next);
+    }
+}
+
+/// The lowercase `scheme://host:port` of the url.
+fn origin(url: &str) -> Option<String> {
+    let (scheme, rest) = url.split_once("://")?;
+    let authority = &rest[..rest
+        .find(|c| c == '/' || c == '?' || c == '#')
+        .unwrap_or(rest.len())];
+    let host = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    Some(format!("{scheme}://{host}").to_ascii_lowercase())
+}
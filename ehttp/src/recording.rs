@@ -0,0 +1,168 @@
+//! Record requests and their responses, and replay them later.
+//!
+//! Record against a live API once, save the recording to a JSON file,
+//! and then run your tests offline against the recording.
+//!
+//! Requires the `json` feature to be enabled.
+//!
+//! Example:
+//! ```
+//! use ehttp::recording::{Matching, Recording};
+//!
+//! let request = ehttp::Request::get("https://www.example.com/api");
+//! let response = ehttp::Response::new(200, b"hello".to_vec())
+//!     .with_url("https://www.example.com/api")
+//!     .with_header("content-type", "text/plain");
+//!
+//! let mut recording = Recording::new(Matching::MethodAndUrl);
+//! recording.record(&request, &response);
+//! let json = recording.to_json().unwrap();
+//!
+//! let replayed = Recording::from_json(&json, Matching::MethodAndUrl).unwrap();
+//! let replayed_response = replayed.replay(&request).unwrap();
+//! assert_eq!(replayed_response.status, 200);
+//! assert_eq!(replayed_response.content_type(), Some("text/plain"));
+//! assert_eq!(replayed_response.bytes, b"hello");
+//!
+//! assert!(replayed.replay(&ehttp::Request::get("https://www.example.com/other")).is_none());
+//! ```
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Headers, Request, Response};
+
+/// How to decide if a request matches a recorded request.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Matching {
+    /// The method and url must be the same.
+    #[default]
+    MethodAndUrl,
+
+    /// The method, url, headers and body must all be the same.
+    ///
+    /// Headers are compared ignoring their order and the case of their keys.
+    Full,
+}
+
+/// The parts of a [`Request`] that are recorded.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: Headers,
+    pub body: Vec<u8>,
+}
+
+impl RecordedRequest {
+    fn new(request: &Request) -> Self {
+        Self {
+            method: request.method.to_string(),
+            url: request.url.clone(),
+            headers: request.headers.clone(),
+            body: request.body.clone(),
+        }
+    }
+
+    fn matches(&self, request: &Request, matching: Matching) -> bool {
+        let method_and_url = self.method == request.method && self.url == request.url;
+        match matching {
+            Matching::MethodAndUrl => method_and_url,
+            Matching::Full => {
+                method_and_url
+                    && self.body == request.body
+                    && normalized(&self.headers) == normalized(&request.headers)
+            }
+        }
+    }
+}
+
+fn normalized(headers: &Headers) -> Vec<(String, &str)> {
+    let mut headers: Vec<_> = headers
+        .headers
+        .iter()
+        .map(|(k, v)| (k.to_lowercase(), v.as_str()))
+        .collect();
+    headers.sort();
+    headers
+}
+
+/// A recorded request and the response to it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Entry {
+    pub request: RecordedRequest,
+    pub response: Response,
+}
+
+/// A list of recorded requests and their responses.
+#[derive(Clone, Debug, Default)]
+pub struct Recording {
+    pub entries: Vec<Entry>,
+    pub matching: Matching,
+}
+
+impl Recording {
+    pub fn new(matching: Matching) -> Self {
+        Self {
+            entries: vec![],
+            matching,
+        }
+    }
+
+    /// Record a request and the response to it.
+    pub fn record(&mut self, request: &Request, response: &Response) {
+        self.entries.push(Entry {
+            request: RecordedRequest::new(request),
+            response: response.clone(),
+        });
+    }
+
+    /// Get the recorded response of the first recorded request that matches the given request.
+    pub fn replay(&self, request: &Request) -> Option<Response> {
+        self.entries
+            .iter()
+            .find(|entry| entry.request.matches(request, self.matching))
+            .map(|entry| entry.response.clone())
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(&self.entries)
+    }
+
+    pub fn from_json(json: &str, matching: Matching) -> serde_json::Result<Self> {
+        Ok(Self {
+            entries: serde_json::from_str(json)?,
+            matching,
+        })
+    }
+
+    /// Save the recording as a JSON file.
+    ///
+    /// Only available when compiling for native.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> crate::Result<()> {
+        let path = path.as_ref();
+        let json = self.to_json().map_err(|err| err.to_string())?;
+        std::fs::write(path, json).map_err(|err| format!("Failed to write {path:?}: {err}"))
+    }
+
+    /// Load a recording from a JSON file created with [`Self::save`].
+    ///
+    /// Only available when compiling for native.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(path: impl AsRef<std::path::Path>, matching: Matching) -> crate::Result<Self> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path)
+            .map_err(|err| format!("Failed to read {path:?}: {err}"))?;
+        Self::from_json(&json, matching).map_err(|err| format!("Failed to parse {path:?}: {err}"))
+    }
+
+    /// Perform the request with [`crate::fetch_blocking`] and record the response.
+    ///
+    /// Only available when compiling for native.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn fetch_blocking(&mut self, request: &Request) -> crate::Result<Response> {
+        let response = crate::fetch_blocking(request)?;
+        self.record(request, &response);
+        Ok(response)
+    }
+}
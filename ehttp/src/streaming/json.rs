@@ -0,0 +1,220 @@
+use std::marker::PhantomData;
+
+use serde::de::DeserializeOwned;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// Waiting for the opening `[`.
+    Start,
+
+    /// Just after the opening `[`.
+    ValueOrEnd,
+
+    /// Just after a `,`.
+    Value,
+
+    /// Just after an element.
+    CommaOrEnd,
+
+    /// We have seen the closing `]`.
+    Done,
+
+    /// We encountered an error, and will not parse anything else.
+    Failed,
+}
+
+/// How much of the element at the start of the buffer we have scanned so far,
+/// so that each byte is only looked at once, however many chunks the element is split over.
+#[derive(Default)]
+struct Scan {
+    len: usize,
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+}
+
+/// Incrementally parses the elements of a top-level JSON array as the chunks of it arrive.
+///
+/// This lets you handle huge JSON arrays without first buffering the whole response body.
+/// Elements may be split across chunks at any byte.
+///
+/// Requires the `json` feature to be enabled.
+///
+/// ```
+/// let mut parser = ehttp::streaming::JsonArrayParser::<u32>::new();
+/// let mut elements = vec![];
+/// for chunk in ["[1", "2, 3", "4,", " 56]"] {
+///     for element in parser.feed(chunk.as_bytes()) {
+///         elements.push(element.unwrap());
+///     }
+/// }
+/// assert!(parser.finish().is_ok());
+/// assert_eq!(elements, vec![12, 34, 56]);
+/// ```
+pub struct JsonArrayParser<T> {
+    buffer: Vec<u8>,
+    state: State,
+    scan: Scan,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T: DeserializeOwned> Default for JsonArrayParser<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: DeserializeOwned> JsonArrayParser<T> {
+    pub fn new() -> Self {
+        Self {
+            buffer: vec![],
+            state: State::Start,
+            scan: Scan::default(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Feed the next chunk of the body, and get back all the elements that were completed by it.
+    ///
+    /// After the first error, no more elements will be returned.
+    ///
+    /// Objects, arrays, strings and literals are returned as soon as their last byte arrives,
+    /// while a number is only known to be complete once we see what follows it:
+    ///
+    /// ```
+    /// let mut parser = ehttp::streaming::JsonArrayParser::<serde_json::Value>::new();
+    /// assert_eq!(parser.feed(br#"[{"a": "]}"}"#).len(), 1);
+    /// assert_eq!(parser.feed(br#", "x", true"#).len(), 2);
+    /// assert_eq!(parser.feed(b", 12").len(), 0);
+    /// assert_eq!(parser.feed(b"3]").len(), 1);
+    /// assert!(parser.finish().is_ok());
+    /// ```
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<crate::Result<T>> {
+        self.buffer.extend_from_slice(chunk);
+
+        let mut elements = vec![];
+        let mut pos = 0;
+        while let Some(element) = self.next_element(&mut pos) {
+            let is_err = element.is_err();
+            elements.push(element);
+            if is_err {
+                break;
+            }
+        }
+        self.buffer.drain(..pos);
+        elements
+    }
+
+    /// Call this once the body has ended, to check that the array was complete.
+    pub fn finish(&self) -> crate::Result<()> {
+        match self.state {
+            State::Done => Ok(()),
+            State::Failed => Err("Failed to parse JSON array".to_owned()),
+            _ => Err("JSON array ended unexpectedly".to_owned()),
+        }
+    }
+
+    fn fail(&mut self, err: String) -> Option<crate::Result<T>> {
+        self.state = State::Failed;
+        Some(Err(err))
+    }
+
+    fn next_element(&mut self, pos: &mut usize) -> Option<crate::Result<T>> {
+        loop {
+            while self.buffer.get(*pos).map_or(false, u8::is_ascii_whitespace) {
+                *pos += 1;
+            }
+            let byte = *self.buffer.get(*pos)?;
+
+            match self.state {
+                State::Start => {
+                    if byte != b'[' {
+                        return self.fail("Expected a JSON array".to_owned());
+                    }
+                    *pos += 1;
+                    self.state = State::ValueOrEnd;
+                }
+                State::CommaOrEnd | State::ValueOrEnd if byte == b']' => {
+                    *pos += 1;
+                    self.state = State::Done;
+                }
+                State::CommaOrEnd => {
+                    if byte != b',' {
+                        return self.fail("Expected ',' or ']' in JSON array".to_owned());
+                    }
+                    *pos += 1;
+                    self.state = State::Value;
+                }
+                State::Value | State::ValueOrEnd => {
+                    let end = self.element_end(*pos)?;
+                    let element = &self.buffer[*pos..end];
+                    self.scan = Scan::default();
+                    return match serde_json::from_slice(element) {
+                        Ok(value) => {
+                            *pos = end;
+                            self.state = State::CommaOrEnd;
+                            Some(Ok(value))
+                        }
+                        Err(err) => self.fail(format!("Failed to parse JSON array element: {err}")),
+                    };
+                }
+                State::Done => {
+                    return self.fail("Unexpected data after the end of the JSON array".to_owned());
+                }
+                State::Failed => {
+                    return None;
+                }
+            }
+        }
+    }
+
+    /// Where the element starting at `start` ends, once we have all of it.
+    fn element_end(&mut self, start: usize) -> Option<usize> {
+        let element = &self.buffer[start..];
+        let scan = &mut self.scan;
+        match element[0] {
+            b'{' | b'[' | b'"' => {
+                while let Some(&byte) = element.get(scan.len) {
+                    scan.len += 1;
+                    if scan.in_string {
+                        if scan.escaped {
+                            scan.escaped = false;
+                        } else if byte == b'\\' {
+                            scan.escaped = true;
+                        } else if byte == b'"' {
+                            scan.in_string = false;
+                        }
+                    } else {
+                        match byte {
+                            b'"' => scan.in_string = true,
+                            b'{' | b'[' => scan.depth += 1,
+                            b'}' | b']' => scan.depth = scan.depth.saturating_sub(1),
+                            _ => {}
+                        }
+                    }
+                    if !scan.in_string && scan.depth == 0 {
+                        return Some(start + scan.len);
+                    }
+                }
+                None
+            }
+            b't' | b'n' => (element.len() >= 4).then_some(start + 4),
+            b'f' => (element.len() >= 5).then_some(start + 5),
+            _ => {
+                // A number, e.g. `12` could be the start of `123`:
+                let is_number_byte = |byte: &u8| byte.is_ascii_digit() || b"+-.eE".contains(byte);
+                scan.len = scan.len.max(1);
+                match element[scan.len..]
+                    .iter()
+                    .position(|byte| !is_number_byte(byte))
+                {
+                    Some(len) => Some(start + scan.len + len),
+                    None => {
+                        scan.len = element.len();
+                        None
+                    }
+                }
+            }
+        }
+    }
+}
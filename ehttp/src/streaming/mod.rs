@@ -36,6 +36,10 @@
 //!         ehttp::streaming::Part::Chunk(chunk) => {
 //!             your_chunk_handler(chunk)
 //!         }
+//!         ehttp::streaming::Part::Trailers(trailers) => {
+//!             println!("Trailers: {trailers:?}");
+//!             std::ops::ControlFlow::Continue(())
+//!         }
 //!     }
 //! });
 //! ```
@@ -51,7 +55,55 @@ use crate::Request;
 /// Performs a HTTP requests and calls the given callback once for the initial response,
 /// and then once for each chunk in the response body.
 ///
-/// You can abort the fetch by returning [`ControlFlow::Break`] from the callback.
+/// You can abort the fetch by returning [`ControlFlow::Break`] from the callback,
+/// e.g. when the user cancels, or you found what you were looking for.
+/// The callback is then not called again, and the connection is closed,
+/// so the rest of the body is never downloaded.
+///
+/// ```
+/// # use std::io::{Read as _, Write as _};
+/// # let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+/// # let url = format!("http://{}/huge", listener.local_addr().unwrap());
+/// # let (server_tx, server_rx) = std::sync::mpsc::channel();
+/// # std::thread::spawn(move || {
+/// #     let (mut stream, _) = listener.accept().unwrap();
+/// #     let mut buf = [0; 1024];
+/// #     let _ = stream.read(&mut buf).unwrap();
+/// #     stream.set_write_timeout(Some(std::time::Duration::from_secs(10))).unwrap();
+/// #     stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 1000000000\r\n\r\n").unwrap();
+/// #     let chunk = vec![b'x'; 64 * 1024];
+/// #     let mut sent = 0;
+/// #     let err = loop {
+/// #         match stream.write_all(&chunk) {
+/// #             Ok(()) => sent += chunk.len(),
+/// #             Err(err) => break err,
+/// #         }
+/// #     };
+/// #     server_tx.send((sent, err.kind())).unwrap();
+/// # });
+/// use std::ops::ControlFlow;
+/// use ehttp::streaming::Part;
+///
+/// let (tx, rx) = std::sync::mpsc::channel();
+/// ehttp::streaming::fetch(ehttp::Request::get(url), move |part| {
+///     let part = part.unwrap();
+///     let is_chunk = matches!(part, Part::Chunk(_));
+///     tx.send(part).unwrap();
+///     if is_chunk {
+///         ControlFlow::Break(()) // We only wanted the start of the body
+///     } else {
+///         ControlFlow::Continue(())
+///     }
+/// });
+///
+/// let parts: Vec<Part> = rx.iter().collect(); // Ends when the callback is dropped
+/// assert!(matches!(parts.as_slice(), [Part::Response(_), Part::Chunk(_)]));
+///
+/// // The server fails to send the rest, as the connection was closed:
+/// let (sent, err) = server_rx.recv().unwrap();
+/// assert!(matches!(err, std::io::ErrorKind::BrokenPipe | std::io::ErrorKind::ConnectionReset), "{err:?}");
+/// assert!(sent < 1_000_000_000);
+/// ```
 pub fn fetch(
     request: Request,
     on_data: impl 'static + Send + Fn(crate::Result<types::Part>) -> ControlFlow<()>,
@@ -63,10 +115,62 @@ pub fn fetch(
     web::fetch_streaming(request, Box::new(on_data));
 }
 
+/// Performs a HTTP request for a top-level JSON array,
+/// and calls the given callback once for each element as it is streamed in.
+///
+/// This avoids having to buffer the whole response body.
+/// A response that isn't a 2xx, or that fails to parse, results in a single `Err`.
+///
+/// You can abort the fetch by returning [`ControlFlow::Break`] from the callback.
+///
+/// Requires the `json` feature to be enabled.
+#[cfg(feature = "json")]
+pub fn fetch_json_array<T>(
+    request: Request,
+    on_element: impl 'static + Send + Fn(crate::Result<T>) -> ControlFlow<()>,
+) where
+    T: 'static + serde::de::DeserializeOwned,
+{
+    let parser = std::sync::Mutex::new(JsonArrayParser::<T>::new());
+    fetch(request, move |part| match part {
+        Err(err) => {
+            on_element(Err(err));
+            ControlFlow::Break(())
+        }
+        Ok(Part::Response(response)) => {
+            if response.ok {
+                ControlFlow::Continue(())
+            } else {
+                on_element(Err(format!("{} {}", response.status, response.status_text)));
+                ControlFlow::Break(())
+            }
+        }
+        Ok(Part::Chunk(chunk)) => {
+            let mut parser = parser.lock().unwrap();
+            if chunk.is_empty() {
+                if let Err(err) = parser.finish() {
+                    on_element(Err(err));
+                }
+                return ControlFlow::Break(());
+            }
+            for element in parser.feed(&chunk) {
+                let is_err = element.is_err();
+                if on_element(element).is_break() || is_err {
+                    return ControlFlow::Break(());
+                }
+            }
+            ControlFlow::Continue(())
+        }
+        Ok(Part::Trailers(_)) => ControlFlow::Continue(()),
+    });
+}
+
 #[cfg(not(target_arch = "wasm32"))]
 mod native;
+#[cfg(all(not(target_arch = "wasm32"), feature = "native-async"))]
+pub use native::fetch_async_streaming;
 #[cfg(not(target_arch = "wasm32"))]
-pub use native::fetch_streaming_blocking;
+pub use native::{fetch_streaming_blocking, fetch_to_writer_blocking};
 
 #[cfg(target_arch = "wasm32")]
 mod web;
@@ -76,3 +180,9 @@ pub use web::fetch_async_streaming;
 mod types;
 
 pub use self::types::Part;
+
+#[cfg(feature = "json")]
+mod json;
+
+#[cfg(feature = "json")]
+pub use self::json::JsonArrayParser;
@@ -1,69 +1,60 @@
+use std::io::{Read, Write};
 use std::ops::ControlFlow;
 
+use crate::native::{body_read_error, send, send_with_trailers};
+use crate::types::PartialResponse;
 use crate::Request;
 
 use super::Part;
-use crate::types::PartialResponse;
+
+#[cfg(feature = "checksum")]
+use crate::checksum::Sha256Verifier;
 
 pub fn fetch_streaming_blocking(
     request: Request,
     on_data: Box<dyn Fn(crate::Result<Part>) -> ControlFlow<()> + Send>,
 ) {
-    let mut req = ureq::request(&request.method, &request.url);
-
-    for (k, v) in &request.headers {
-        req = req.set(k, v);
-    }
-
-    let resp = if request.body.is_empty() {
-        req.call()
-    } else {
-        req.send_bytes(&request.body)
-    };
-
-    let (ok, resp) = match resp {
-        Ok(resp) => (true, resp),
-        Err(ureq::Error::Status(_, resp)) => (false, resp), // Still read the body on e.g. 404
-        Err(ureq::Error::Transport(err)) => {
-            on_data(Err(err.to_string()));
+    let (response, mut reader, trailers) = match send_with_trailers(&request) {
+        Ok(result) => result,
+        Err(err) => {
+            on_data(Err(err));
             return;
         }
     };
 
-    let url = resp.get_url().to_owned();
-    let status = resp.status();
-    let status_text = resp.status_text().to_owned();
-    let mut headers = crate::Headers::default();
-    for key in &resp.headers_names() {
-        if let Some(value) = resp.header(key) {
-            headers.insert(key.to_ascii_lowercase(), value.to_owned());
-        }
-    }
-    headers.sort(); // It reads nicer, and matches web backend.
-
-    let response = PartialResponse {
-        url,
-        ok,
-        status,
-        status_text,
-        headers,
-    };
     if on_data(Ok(Part::Response(response))).is_break() {
         return;
     };
 
-    let mut reader = resp.into_reader();
+    #[cfg(feature = "checksum")]
+    let mut verifier = request.expected_sha256.as_deref().map(Sha256Verifier::new);
+
     loop {
         let mut buf = vec![0; 2048];
         match reader.read(&mut buf) {
             Ok(n) if n > 0 => {
                 // clone data from buffer and clear it
                 let chunk = buf[..n].to_vec();
+                #[cfg(feature = "checksum")]
+                if let Some(verifier) = &mut verifier {
+                    verifier.update(&chunk);
+                }
                 if on_data(Ok(Part::Chunk(chunk))).is_break() {
                     return;
                 };
             }
             Ok(_) => {
+                #[cfg(feature = "checksum")]
+                if let Some(verifier) = verifier.take() {
+                    if let Err(err) = verifier.finish() {
+                        on_data(Err(err));
+                        return;
+                    }
+                }
+                let trailers = std::mem::take(&mut *trailers.lock().unwrap());
+                if !trailers.is_empty() && on_data(Ok(Part::Trailers(trailers))).is_break() {
+                    return;
+                }
                 on_data(Ok(Part::Chunk(vec![])));
                 break;
             }
@@ -73,7 +64,7 @@ pub fn fetch_streaming_blocking(
                     on_data(Ok(Part::Chunk(vec![])));
                     break;
                 } else {
-                    on_data(Err(format!("Failed to read response body: {err}")));
+                    on_data(Err(body_read_error(&err)));
                     return;
                 }
             }
@@ -81,6 +72,279 @@ pub fn fetch_streaming_blocking(
     }
 }
 
+/// Performs a HTTP request and writes the response body into `writer` as it is streamed in,
+/// without buffering the whole body.
+///
+/// This works with anything that implements [`Write`], e.g. a file, a socket, a compressor or a hasher.
+///
+/// Returns the response status and headers, and the number of bytes written.
+/// Like with [`crate::fetch_blocking`], the body is written even if the response isn't a 2xx,
+/// so check [`PartialResponse::ok`].
+///
+/// With [`Request::truncate_body_at`], at most that many bytes are written,
+/// and the rest of the body is never downloaded.
+///
+/// Here a download is hashed as it arrives, without ever holding all of it in memory:
+///
+/// ```
+/// # use std::io::{Read as _, Write as _};
+/// # let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+/// # let url = format!("http://{}/download", listener.local_addr().unwrap());
+/// # std::thread::spawn(move || {
+/// #     let (mut stream, _) = listener.accept().unwrap();
+/// #     let _ = stream.read(&mut [0; 1024]).unwrap();
+/// #     let body: Vec<u8> = (0..100_000).map(|i| (i % 251) as u8).collect();
+/// #     stream.write_all(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n").unwrap();
+/// #     for chunk in body.chunks(4096) {
+/// #         write!(stream, "{:x}\r\n", chunk.len()).unwrap();
+/// #         stream.write_all(chunk).unwrap();
+/// #         stream.write_all(b"\r\n").unwrap();
+/// #     }
+/// #     stream.write_all(b"0\r\n\r\n").unwrap();
+/// # });
+/// /// The 64-bit FNV-1a hash of everything written to it.
+/// struct Fnv1a(u64);
+///
+/// impl std::io::Write for Fnv1a {
+///     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+///         for &byte in buf {
+///             self.0 = (self.0 ^ byte as u64).wrapping_mul(0x100000001b3);
+///         }
+///         Ok(buf.len())
+///     }
+///
+///     fn flush(&mut self) -> std::io::Result<()> {
+///         Ok(())
+///     }
+/// }
+///
+/// let mut hasher = Fnv1a(0xcbf29ce484222325);
+/// let (response, num_bytes) =
+///     ehttp::streaming::fetch_to_writer_blocking(&ehttp::Request::get(url), &mut hasher)?;
+/// assert!(response.ok);
+/// assert_eq!(num_bytes, 100_000);
+/// assert_eq!(hasher.0, 0xe796b565d8a90d28);
+/// # Ok::<(), ehttp::Error>(())
+/// ```
+///
+/// A server that stalls in the middle of the body is a read timeout,
+/// and [`Request::truncate_body_at`] stops the download early:
+///
+/// ```
+/// # use std::io::{Read as _, Write as _};
+/// # use std::time::Duration;
+/// # let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+/// # let url = format!("http://{}", listener.local_addr().unwrap());
+/// # std::thread::spawn(move || {
+/// #     for stream in listener.incoming() {
+/// #         let mut stream = stream.unwrap();
+/// #         std::thread::spawn(move || {
+/// #             let _ = stream.read(&mut [0; 1024]).unwrap();
+/// #             stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nhello").unwrap();
+/// #             std::thread::sleep(Duration::from_secs(2)); // Never send the rest
+/// #         });
+/// #     }
+/// # });
+/// let request = ehttp::Request::get(&url).with_timeout(Duration::from_millis(200));
+/// let error = ehttp::streaming::fetch_to_writer_blocking(&request, &mut Vec::new()).unwrap_err();
+/// assert_eq!(ehttp::timeout_kind(&error), Some(ehttp::TimeoutKind::Read), "{error}");
+///
+/// let mut body = Vec::new();
+/// let (_, num_bytes) =
+///     ehttp::streaming::fetch_to_writer_blocking(&request.truncate_body_at(3), &mut body)?;
+/// assert_eq!(num_bytes, 3);
+/// assert_eq!(body, b"hel");
+/// # Ok::<(), ehttp::Error>(())
+/// ```
+///
+/// Only available when compiling for native.
+pub fn fetch_to_writer_blocking(
+    request: &Request,
+    writer: &mut impl Write,
+) -> crate::Result<(PartialResponse, u64)> {
+    let (response, mut reader) = send(request)?;
+
+    #[cfg(feature = "checksum")]
+    if let Some(expected) = &request.expected_sha256 {
+        let mut writer = HashingWriter {
+            writer,
+            verifier: Sha256Verifier::new(expected),
+        };
+        let (num_bytes, truncated) = copy_body(request, &mut reader, &mut writer)?;
+        if !truncated {
+            writer.verifier.finish()?;
+        }
+        return Ok((response, num_bytes));
+    }
+
+    let (num_bytes, _truncated) = copy_body(request, &mut reader, writer)?;
+    Ok((response, num_bytes))
+}
+
+/// Copy the body to the writer, up to [`Request::truncate_body_at`].
+///
+/// Returns the number of bytes written, and whether the body was truncated.
+fn copy_body(
+    request: &Request,
+    reader: &mut impl Read,
+    writer: &mut impl Write,
+) -> crate::Result<(u64, bool)> {
+    let max_bytes = request.truncate_body_at.map_or(u64::MAX, |max| max as u64);
+    let mut buf = vec![0; 64 * 1024];
+    let mut num_bytes = 0;
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => return Ok((num_bytes, false)),
+            Ok(n) => n,
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(err)
+                if request.method == "HEAD" && err.kind() == std::io::ErrorKind::UnexpectedEof =>
+            {
+                // We don't really expect a body for HEAD requests, so this is fine.
+                return Ok((num_bytes, false));
+            }
+            Err(err) => return Err(body_read_error(&err)),
+        };
+        let keep = (n as u64).min(max_bytes - num_bytes) as usize;
+        writer
+            .write_all(&buf[..keep])
+            .map_err(|err| format!("Failed to write response body: {err}"))?;
+        num_bytes += keep as u64;
+        if keep < n {
+            return Ok((num_bytes, true));
+        }
+    }
+}
+
+/// Hashes everything that is written through it.
+#[cfg(feature = "checksum")]
+struct HashingWriter<'w, W> {
+    writer: &'w mut W,
+    verifier: Sha256Verifier,
+}
+
+#[cfg(feature = "checksum")]
+impl<'w, W: Write> Write for HashingWriter<'w, W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = self.writer.write(buf)?;
+        self.verifier.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Performs a HTTP request and returns the response as a [`Stream`](futures_util::Stream),
+/// starting with a [`Part::Response`] followed by the chunks of the body, and any [`Part::Trailers`].
+///
+/// The body is only read from the socket as fast as the stream is polled:
+/// at most two chunks are read ahead of the consumer (one waiting in the stream,
+/// and one that the reading thread holds until there is room for it),
+/// so a slow consumer applies backpressure all the way to the server instead of the chunks piling up in memory.
+///
+/// Unlike [`super::fetch`], the end of the body is not signaled by an empty chunk: the stream just ends.
+/// Dropping the stream aborts the download.
+///
+/// Here the server can't send more than what fits in the socket buffers while the consumer is busy:
+///
+/// ```
+/// # use std::io::{Read as _, Write as _};
+/// # use std::sync::{atomic::{AtomicUsize, Ordering}, Arc};
+/// # use std::time::Duration;
+/// # fn block_on<F: std::future::Future>(future: F) -> F::Output {
+/// #     struct Unpark(std::thread::Thread);
+/// #     impl std::task::Wake for Unpark {
+/// #         fn wake(self: Arc<Self>) {
+/// #             self.0.unpark();
+/// #         }
+/// #     }
+/// #     let waker = Arc::new(Unpark(std::thread::current())).into();
+/// #     let mut cx = std::task::Context::from_waker(&waker);
+/// #     let mut future = std::pin::pin!(future);
+/// #     loop {
+/// #         match future.as_mut().poll(&mut cx) {
+/// #             std::task::Poll::Ready(output) => return output,
+/// #             std::task::Poll::Pending => std::thread::park(),
+/// #         }
+/// #     }
+/// # }
+/// use futures_util::StreamExt as _;
+///
+/// const BODY_SIZE: usize = 64 * 1024 * 1024;
+///
+/// // A server that sends the body as fast as it is let to, and counts how much it has sent:
+/// let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+/// let url = format!("http://{}", listener.local_addr().unwrap());
+/// let sent = Arc::new(AtomicUsize::new(0));
+/// let server_sent = sent.clone();
+/// std::thread::spawn(move || {
+///     let (mut stream, _) = listener.accept().unwrap();
+///     let _ = stream.read(&mut [0; 1024]).unwrap();
+///     write!(stream, "HTTP/1.1 200 OK\r\nContent-Length: {BODY_SIZE}\r\n\r\n").unwrap();
+///     let block = vec![b'x'; 64 * 1024];
+///     while server_sent.load(Ordering::SeqCst) < BODY_SIZE {
+///         stream.write_all(&block).unwrap();
+///         server_sent.fetch_add(block.len(), Ordering::SeqCst);
+///     }
+/// });
+///
+/// block_on(async {
+///     let stream = ehttp::streaming::fetch_async_streaming(&ehttp::Request::get(url)).await?;
+///     let mut stream = std::pin::pin!(stream);
+///     assert!(matches!(stream.next().await, Some(Ok(ehttp::streaming::Part::Response(_)))));
+///
+///     // A slow consumer:
+///     std::thread::sleep(Duration::from_millis(500));
+///     let sent_while_busy = sent.load(Ordering::SeqCst);
+///     assert!(sent_while_busy < BODY_SIZE / 2, "{}", sent_while_busy);
+///
+///     let mut received = 0;
+///     while let Some(part) = stream.next().await {
+///         if let ehttp::streaming::Part::Chunk(chunk) = part? {
+///             received += chunk.len();
+///         }
+///     }
+///     assert_eq!(received, BODY_SIZE);
+///     Ok::<(), ehttp::Error>(())
+/// })?;
+/// # Ok::<(), ehttp::Error>(())
+/// ```
+///
+/// Only available when compiling for native with the `native-async` feature enabled.
+/// On web the same function is available, backed by the browser's `ReadableStream`.
+#[cfg(feature = "native-async")]
+pub async fn fetch_async_streaming(
+    request: &Request,
+) -> crate::Result<impl futures_util::Stream<Item = crate::Result<Part>>> {
+    use futures_util::StreamExt as _;
+
+    // A capacity of one blocks the reading thread until the consumer has taken the previous chunk
+    // (a capacity of zero isn't supported).
+    let (tx, rx) = async_channel::bounded(1);
+
+    let request = request.clone();
+    std::thread::Builder::new()
+        .name("ehttp".to_owned())
+        .spawn(move || {
+            fetch_streaming_blocking(
+                request,
+                Box::new(move |part| match part {
+                    Ok(Part::Chunk(chunk)) if chunk.is_empty() => ControlFlow::Break(()),
+                    part => match tx.send_blocking(part) {
+                        Ok(()) => ControlFlow::Continue(()),
+                        Err(_) => ControlFlow::Break(()), // The stream was dropped
+                    },
+                }),
+            );
+        })
+        .map_err(|err| format!("Failed to spawn ehttp thread: {err}"))?;
+
+    let response = rx.recv().await.map_err(|err| err.to_string())??;
+    Ok(futures_util::stream::once(futures_util::future::ready(Ok(response))).chain(rx))
+}
+
 pub(crate) fn fetch_streaming(
     request: Request,
     on_data: Box<dyn Fn(crate::Result<Part>) -> ControlFlow<()> + Send>,
@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+
 use crate::types::PartialResponse;
 
 /// A piece streamed by [`crate::streaming::fetch`].
@@ -11,4 +13,11 @@ pub enum Part {
     ///
     /// If the chunk is empty, that means the `on_data` callback will not receive any more data.
     Chunk(Vec<u8>),
+
+    /// The trailers sent after the body, see [`crate::Response::trailers`].
+    ///
+    /// Only sent if there are any, after the last non-empty [`Self::Chunk`]
+    /// and before the final empty one.
+    /// Never sent on web, where the browser doesn't expose trailers.
+    Trailers(BTreeMap<String, String>),
 }
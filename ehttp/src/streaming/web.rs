@@ -37,7 +37,7 @@ async fn fetch_jsvalue_stream(
     // returns a `Part::Response` followed by all the chunks in `body` as `Part::Chunk`
     Ok(
         futures_util::stream::once(futures_util::future::ready(Ok(Part::Response(
-            get_response_base(&response)?,
+            get_response_base(request, &response)?,
         ))))
         .chain(
             body.into_stream()
@@ -59,9 +59,19 @@ pub(crate) fn fetch_streaming(
             }
         };
 
+        #[cfg(feature = "checksum")]
+        let mut verifier = request
+            .expected_sha256
+            .as_deref()
+            .map(crate::checksum::Sha256Verifier::new);
+
         while let Some(chunk) = stream.next().await {
             match chunk {
                 Ok(chunk) => {
+                    #[cfg(feature = "checksum")]
+                    if let (Some(verifier), Part::Chunk(bytes)) = (&mut verifier, &chunk) {
+                        verifier.update(bytes);
+                    }
                     if on_data(Ok(chunk)).is_break() {
                         return;
                     }
@@ -73,6 +83,14 @@ pub(crate) fn fetch_streaming(
             }
         }
 
+        #[cfg(feature = "checksum")]
+        if let Some(verifier) = verifier {
+            if let Err(err) = verifier.finish() {
+                on_data(Err(err));
+                return;
+            }
+        }
+
         on_data(Ok(Part::Chunk(vec![])));
     })
 }
@@ -0,0 +1,240 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+
+use base64::Engine as _;
+use sha1::{Digest as _, Sha1};
+
+use crate::native::{combined_headers, connect_tcp, read_response_head};
+use crate::own_client::{raw_stream, Stream};
+use crate::{Request, Response};
+
+/// The GUID that the server appends to our key for the `Sec-WebSocket-Accept`, see RFC 6455.
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// Perform the HTTP handshake of a WebSocket connection, and hand back the connection.
+///
+/// Sends the request as a `GET` with `Upgrade: websocket`, and checks that the server
+/// responds with `101 Switching Protocols` and the expected `Sec-WebSocket-Accept`.
+/// Any other response is an error.
+///
+/// The returned [`UpgradedStream`] is the raw connection: ehttp doesn't frame WebSocket messages,
+/// so give it to a WebSocket library (e.g. `tungstenite::WebSocket::from_raw_socket`) to do that.
+/// Add any `Sec-WebSocket-Protocol` or `Sec-WebSocket-Extensions` you want to [`Request::headers`],
+/// and check what the server picked in the headers of the returned [`Response`].
+///
+/// `ws://` and `wss://` urls are sent as `http://` and `https://`.
+/// [`Request::timeout`] only applies to the handshake, not to the returned stream.
+/// Proxies are not supported, and redirects are not followed.
+///
+/// ```
+/// # use std::io::{Read as _, Write as _};
+/// # use base64::Engine as _;
+/// # use sha1::Digest as _;
+/// # let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+/// # let url = format!("ws://{}/chat", listener.local_addr().unwrap());
+/// # let server = std::thread::spawn(move || {
+/// #     let (mut stream, _) = listener.accept().unwrap();
+/// #     let mut head = vec![];
+/// #     while !head.ends_with(b"\r\n\r\n") {
+/// #         let mut byte = [0];
+/// #         stream.read_exact(&mut byte).unwrap();
+/// #         head.push(byte[0]);
+/// #     }
+/// #     let head = String::from_utf8(head).unwrap();
+/// #     let key = head.lines().find_map(|line| line.strip_prefix("Sec-WebSocket-Key: ")).unwrap();
+/// #     let digest = sha1::Sha1::digest(format!("{key}258EAFA5-E914-47DA-95CA-C5AB0DC85B11"));
+/// #     let accept = base64::engine::general_purpose::STANDARD.encode(digest);
+/// #     write!(stream, "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {accept}\r\n\r\nhi").unwrap();
+/// #     let mut reply = [0; 2];
+/// #     stream.read_exact(&mut reply).unwrap();
+/// #     (head, reply)
+/// # });
+/// let (response, mut stream) = ehttp::upgrade(&ehttp::Request::get(url))?;
+/// assert_eq!(response.status, 101);
+///
+/// // Now it's up to a WebSocket library to speak WebSocket over the stream:
+/// let mut greeting = [0; 2];
+/// stream.read_exact(&mut greeting).unwrap();
+/// assert_eq!(&greeting, b"hi");
+/// stream.write_all(b"yo").unwrap();
+///
+/// # let (head, reply) = server.join().unwrap();
+/// # assert!(head.starts_with("GET /chat HTTP/1.1\r\n"), "{head}");
+/// # assert!(head.contains("\r\nUpgrade: websocket\r\n"), "{head}");
+/// # assert!(head.contains("\r\nSec-WebSocket-Version: 13\r\n"), "{head}");
+/// # assert_eq!(&reply, b"yo");
+/// # Ok::<(), ehttp::Error>(())
+/// ```
+///
+/// A server that doesn't compute the right `Sec-WebSocket-Accept` is rejected:
+///
+/// ```
+/// # use std::io::{Read as _, Write as _};
+/// # let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+/// # let url = format!("ws://{}/", listener.local_addr().unwrap());
+/// # std::thread::spawn(move || {
+/// #     let (mut stream, _) = listener.accept().unwrap();
+/// #     let _ = stream.read(&mut [0; 1024]).unwrap();
+/// #     stream.write_all(b"HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n\r\n").unwrap();
+/// # });
+/// let error = ehttp::upgrade(&ehttp::Request::get(url)).unwrap_err();
+/// assert!(error.contains("Sec-WebSocket-Accept"), "{error}");
+/// ```
+///
+/// Only available when compiling for native.
+pub fn upgrade(request: &Request) -> crate::Result<(Response, UpgradedStream)> {
+    let start = std::time::Instant::now();
+
+    let mut url = url::Url::parse(&request.url).map_err(|err| format!("Bad url: {err}"))?;
+    let is_https = match url.scheme() {
+        "http" | "ws" => false,
+        "https" | "wss" => true,
+        scheme => return Err(format!("Can't upgrade {scheme}:// urls to WebSocket")),
+    };
+    if url.scheme().starts_with("ws") {
+        let scheme = if is_https { "https" } else { "http" };
+        url = url::Url::parse(&format!("{scheme}{}", &url[url::Position::AfterScheme..]))
+            .map_err(|err| format!("Bad url: {err}"))?;
+    }
+    let request = request.clone().with_url(&url);
+    request.check_https_only()?;
+    if request.proxy.is_some() {
+        return Err("WebSocket upgrades can't be sent through a proxy".to_owned());
+    }
+    let request = &*request.signed();
+
+    let tcp = connect_tcp(request, &url)?;
+    let socket = tcp.try_clone().map_err(|err| err.to_string())?;
+    let mut stream = raw_stream(request, &url, tcp, is_https)?;
+
+    let key = websocket_key();
+    let mut head = format!(
+        "GET {} HTTP/1.1\r\n",
+        &url[url::Position::BeforePath..url::Position::AfterQuery]
+    );
+    if request.headers.get("host").is_none() {
+        head += &format!(
+            "Host: {}\r\n",
+            &url[url::Position::BeforeHost..url::Position::AfterPort]
+        );
+    }
+    head += "Upgrade: websocket\r\nConnection: Upgrade\r\n";
+    head += &format!("Sec-WebSocket-Key: {key}\r\nSec-WebSocket-Version: 13\r\n");
+    for (key, value) in combined_headers(&request.headers) {
+        head += &format!("{key}: {value}\r\n");
+    }
+    head += "\r\n";
+
+    let write_err = |err: std::io::Error| format!("Failed to send request: {err}");
+    stream.write_all(head.as_bytes()).map_err(write_err)?;
+    stream.flush().map_err(write_err)?;
+
+    let mut reader = BufReader::new(stream);
+    let response = read_response_head(&mut reader, request)?;
+    if response.status != 101 {
+        return Err(format!(
+            "Expected 101 Switching Protocols from {}, got {} {}",
+            request.url, response.status, response.status_text
+        ));
+    }
+    let is_websocket = response.header("upgrade").map_or(false, |upgrade| {
+        upgrade.trim().eq_ignore_ascii_case("websocket")
+    });
+    if !is_websocket {
+        return Err(format!(
+            "The server didn't upgrade to websocket, but to {:?}",
+            response.header("upgrade").unwrap_or_default()
+        ));
+    }
+    let expected_accept = websocket_accept(&key);
+    if response.header("sec-websocket-accept").map(str::trim) != Some(expected_accept.as_str()) {
+        return Err(format!(
+            "Bad Sec-WebSocket-Accept {:?}, expected {expected_accept:?}",
+            response.header("sec-websocket-accept").unwrap_or_default()
+        ));
+    }
+
+    let io_err = |err: std::io::Error| err.to_string();
+    socket.set_read_timeout(None).map_err(io_err)?;
+    socket.set_write_timeout(None).map_err(io_err)?;
+
+    let mut response = response.complete(vec![]);
+    response.elapsed = start.elapsed();
+    Ok((response, UpgradedStream { reader, socket }))
+}
+
+/// A random base64 `Sec-WebSocket-Key`.
+///
+/// It only has to be unpredictable enough that caches can't confuse handshakes,
+/// so the randomness of the standard library's hash keys is plenty.
+fn websocket_key() -> String {
+    use std::hash::{BuildHasher as _, Hasher as _};
+    let mut nonce = [0_u8; 16];
+    for half in nonce.chunks_mut(8) {
+        let random = std::collections::hash_map::RandomState::new()
+            .build_hasher()
+            .finish();
+        half.copy_from_slice(&random.to_le_bytes());
+    }
+    base64::engine::general_purpose::STANDARD.encode(nonce)
+}
+
+/// The `Sec-WebSocket-Accept` the server has to respond with to our `key`.
+fn websocket_accept(key: &str) -> String {
+    let digest = Sha1::digest(format!("{key}{WEBSOCKET_GUID}"));
+    base64::engine::general_purpose::STANDARD.encode(digest)
+}
+
+/// The connection of a WebSocket after the handshake, see [`upgrade`].
+///
+/// Reading gives the bytes the server sent after the handshake, including any it sent
+/// right after the `101` response. Writing sends bytes to the server as they are.
+///
+/// Only available when compiling for native.
+pub struct UpgradedStream {
+    reader: BufReader<Box<dyn Stream>>,
+    socket: TcpStream,
+}
+
+impl UpgradedStream {
+    /// The underlying socket, e.g. to set read timeouts or to shut down the connection.
+    ///
+    /// Don't read from or write to it directly, as that bypasses TLS and any buffered bytes.
+    pub fn socket(&self) -> &TcpStream {
+        &self.socket
+    }
+}
+
+impl Read for UpgradedStream {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.reader.read(buf)
+    }
+}
+
+impl BufRead for UpgradedStream {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        self.reader.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.reader.consume(amt);
+    }
+}
+
+impl Write for UpgradedStream {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.reader.get_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.reader.get_mut().flush()
+    }
+}
+
+impl std::fmt::Debug for UpgradedStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UpgradedStream")
+            .field("peer_addr", &self.socket.peer_addr().ok())
+            .finish_non_exhaustive()
+    }
+}
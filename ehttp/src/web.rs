@@ -1,7 +1,7 @@
 use wasm_bindgen::prelude::*;
 use wasm_bindgen_futures::JsFuture;
 
-use crate::types::PartialResponse;
+use crate::types::{split_userinfo, without_userinfo, PartialResponse};
 use crate::{Request, Response};
 
 /// Only available when compiling for web.
@@ -40,12 +40,37 @@ pub(crate) fn string_from_fetch_error(value: JsValue) -> String {
 }
 
 pub(crate) async fn fetch_base(request: &Request) -> Result<web_sys::Response, JsValue> {
+    request
+        .check_https_only()
+        .map_err(|err| JsValue::from_str(&err))?;
+    // `fetch` refuses urls with credentials in them.
+    let request = &if split_userinfo(&request.url).is_some() {
+        std::borrow::Cow::Owned(request.clone().basic_auth_from_url())
+    } else {
+        std::borrow::Cow::Borrowed(request)
+    };
+    let request = &request.signed();
+
     let mut opts = web_sys::RequestInit::new();
     opts.method(&request.method);
     opts.mode(web_sys::RequestMode::Cors);
 
-    if !request.body.is_empty() {
-        let body_bytes: &[u8] = &request.body;
+    let body = match &request.body_reader {
+        Some(body_reader) => {
+            use std::io::Read as _;
+            let mut body = vec![];
+            body_reader
+                .take()
+                .map_err(|err| JsValue::from_str(&err))?
+                .read_to_end(&mut body)
+                .map_err(|err| JsValue::from_str(&format!("Failed to read request body: {err}")))?;
+            std::borrow::Cow::Owned(body)
+        }
+        None => std::borrow::Cow::Borrowed(request.body.as_slice()),
+    };
+
+    if !body.is_empty() {
+        let body_bytes: &[u8] = &body;
         let body_array: js_sys::Uint8Array = body_bytes.into();
         let js_value: &JsValue = body_array.as_ref();
         opts.body(Some(js_value));
@@ -54,7 +79,7 @@ pub(crate) async fn fetch_base(request: &Request) -> Result<web_sys::Response, J
     let js_request = web_sys::Request::new_with_str_and_init(&request.url, &opts)?;
 
     for (k, v) in &request.headers {
-        js_request.headers().set(k, v)?;
+        js_request.headers().append(k, v)?;
     }
 
     let window = web_sys::window().unwrap();
@@ -64,7 +89,10 @@ pub(crate) async fn fetch_base(request: &Request) -> Result<web_sys::Response, J
     Ok(response)
 }
 
-pub(crate) fn get_response_base(response: &web_sys::Response) -> Result<PartialResponse, JsValue> {
+pub(crate) fn get_response_base(
+    request: &Request,
+    response: &web_sys::Response,
+) -> Result<PartialResponse, JsValue> {
     // https://developer.mozilla.org/en-US/docs/Web/API/Headers
     // "Note: When Header values are iterated over, […] values from duplicate header names are combined."
     // TODO: support duplicate header names
@@ -91,32 +119,39 @@ pub(crate) fn get_response_base(response: &web_sys::Response) -> Result<PartialR
 
     Ok(PartialResponse {
         url: response.url(),
+        requested_url: without_userinfo(&request.url),
         ok: response.ok(),
         status: response.status(),
         status_text: response.status_text(),
         headers,
+        version: None,
+        decoded_from: None,
     })
 }
 
 /// NOTE: `Ok(…)` is returned on network error.
 /// `Err` is only for failure to use the fetch API.
 async fn fetch_jsvalue(request: &Request) -> Result<Response, JsValue> {
+    let start_ms = js_sys::Date::now();
     let response = fetch_base(request).await?;
 
     let array_buffer = JsFuture::from(response.array_buffer()?).await?;
     let uint8_array = js_sys::Uint8Array::new(&array_buffer);
-    let bytes = uint8_array.to_vec();
+    let mut bytes = uint8_array.to_vec();
+    let truncated = request.truncate_body(&mut bytes);
 
-    let base = get_response_base(&response)?;
+    #[cfg(feature = "checksum")]
+    if let Some(expected) = request.expected_sha256.as_ref().filter(|_| !truncated) {
+        crate::checksum::verify_sha256(expected, &bytes).map_err(|err| JsValue::from_str(&err))?;
+    }
 
-    Ok(Response {
-        url: base.url,
-        ok: base.ok,
-        status: base.status,
-        status_text: base.status_text,
-        bytes,
-        headers: base.headers,
-    })
+    let base = get_response_base(request, &response)?;
+
+    let mut response = base.complete(bytes);
+    response.truncated = truncated;
+    response.elapsed =
+        std::time::Duration::from_secs_f64((js_sys::Date::now() - start_ms).max(0.0) / 1000.0);
+    Ok(response)
 }
 
 /// Spawn an async task.
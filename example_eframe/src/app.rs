@@ -143,6 +143,7 @@ fn on_fetch_part(
                 ControlFlow::Break(()) // some data race - abort download.
             }
         }
+        ehttp::streaming::Part::Trailers(_) => ControlFlow::Continue(()),
     }
 }
 